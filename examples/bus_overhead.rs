@@ -0,0 +1,111 @@
+//! Measures the host-side cost of talking to the bus, against a scripted mock so the
+//! servo and the wire drop out and only the controller's own overhead remains.
+//!
+//! Usage: bus_overhead [READINGS]
+
+use std::{env, error::Error, io, sync::{atomic::{AtomicUsize, Ordering}, Arc}, time::{Duration, Instant}};
+
+use lx16a::{protocol::SERVO_POS_READ, MockReply, MockTransport, ServoController, Transport, DEFAULT_MAX_WRITE_LEN};
+
+// Forwards to the mock, counting the read calls that would each be a syscall on a port
+struct CountingReads {
+    mock: MockTransport,
+    reads: Arc<AtomicUsize>,
+}
+
+impl Transport for CountingReads {
+    type Error = io::Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.mock.write_all(bytes)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.mock.read_exact(buf)
+    }
+
+    // Like a port's `read`: everything already buffered, and at least `min`
+    fn read_some(&mut self, buf: &mut [u8], min: usize) -> io::Result<usize> {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        let len = self.mock.bytes_to_read()?.unwrap_or(0).clamp(min, buf.len());
+        self.mock.read_exact(&mut buf[..len])?;
+        Ok(len)
+    }
+
+    fn bytes_to_read(&mut self) -> io::Result<Option<usize>> {
+        self.mock.bytes_to_read()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.mock.timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.mock.set_timeout(timeout)
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let readings: u32 = env::args().nth(1).map(|n| n.parse()).transpose()?.unwrap_or(100_000);
+    if readings == 0 {
+        return Err("READINGS must be above 0".into());
+    }
+    let reply = MockReply::frame(1, SERVO_POS_READ, &500i16.to_le_bytes());
+
+    let mock = MockTransport::new();
+    let controller = mock.controller(Duration::from_millis(100));
+    for _ in 0..readings {
+        mock.reply(reply.clone());
+    }
+    let begun = Instant::now();
+    for _ in 0..readings {
+        controller.get_position(1, None)?;
+    }
+    let locking = begun.elapsed() / readings;
+
+    for _ in 0..readings {
+        mock.reply(reply.clone());
+    }
+    let begun = Instant::now();
+    for position in controller.position_stream(1, None)?.take(readings as usize) {
+        position?;
+    }
+    let streamed = begun.elapsed() / readings;
+
+    println!("get_position, locking per read  {:>10?}", locking);
+    println!("position_stream                 {:>10?}", streamed);
+
+    for single_shot in [false, true] {
+        let mock = MockTransport::new();
+        let reads = Arc::new(AtomicUsize::new(0));
+        let transport = CountingReads { mock: mock.clone(), reads: reads.clone() };
+        let controller = ServoController::with_transport(Box::new(transport), Duration::from_millis(100))?;
+        controller.set_single_shot_reads(single_shot);
+        for _ in 0..readings {
+            mock.reply(reply.clone());
+        }
+        let begun = Instant::now();
+        for _ in 0..readings {
+            controller.get_position(1, None)?;
+        }
+        println!("{:<31} {:>10?}, reads per reply: {}",
+            if single_shot { "single-shot reads" } else { "header, length and body reads" },
+            begun.elapsed() / readings, reads.load(Ordering::Relaxed) as f32 / readings as f32);
+    }
+
+    let group: Vec<_> = (1..=12).map(|id| (id, 100 + 50 * id as u16, 500)).collect();
+    let groups = (readings / 10).max(1);
+    for max_write_len in [0, DEFAULT_MAX_WRITE_LEN] {
+        let mock = MockTransport::new();
+        let controller = mock.controller(Duration::from_millis(100));
+        controller.set_max_write_len(max_write_len);
+        let begun = Instant::now();
+        for _ in 0..groups {
+            controller.move_group(&group)?;
+        }
+        println!("12-servo move_group, max_write_len {:<3} {:>8?}, writes: {}",
+            max_write_len, begun.elapsed() / groups, mock.writes().len() as u32 / groups);
+    }
+    Ok(())
+}
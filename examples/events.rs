@@ -0,0 +1,36 @@
+//! Unloads servos and prints their events while they are turned by hand.
+//!
+//! Usage: events PORT [ID...]
+
+use std::{env, error::Error, process, time::Duration};
+
+use lx16a::{ServoController, ServoEvent, SubscribeOptions};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let Some(port) = args.next() else {
+        eprintln!("usage: events PORT [ID...]");
+        process::exit(2);
+    };
+    let mut ids = args.map(|id| id.parse()).collect::<Result<Vec<u8>, _>>()?;
+    if ids.is_empty() {
+        ids.push(1);
+    }
+
+    let controller = ServoController::new(&port, 115200, Duration::from_millis(100))?;
+    for &id in &ids {
+        controller.disable_torque(id)?;
+    }
+
+    let subscription = controller.subscribe(&ids, SubscribeOptions::default())?;
+    println!("turn the servos by hand, Ctrl-C to quit");
+    for event in subscription.events() {
+        match event {
+            ServoEvent::PositionChanged { servo_id, position, .. } =>
+                println!("servo {}  position {:4} ({:6.1}°)", servo_id, position.as_ticks(), position.as_degrees()),
+            ServoEvent::FaultRaised { servo_id, faults } => println!("servo {}  faults {:?}", servo_id, faults),
+            ServoEvent::Timeout { servo_id } => println!("servo {}  not answering", servo_id),
+        }
+    }
+    Ok(())
+}
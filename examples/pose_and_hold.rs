@@ -0,0 +1,28 @@
+//! Unloads a set of servos so the arm can be posed by hand, then freezes it in place.
+//!
+//! Usage: pose_and_hold [PORT] [ID...]
+
+use std::{env, error::Error, io, time::Duration};
+
+use lx16a::ServoController;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let port = args.next().unwrap_or_else(|| "COM4".to_string());
+    let ids: Vec<u8> = args.map(|id| id.parse()).collect::<Result<_, _>>()?;
+    let ids = if ids.is_empty() { vec![1] } else { ids };
+
+    let controller = ServoController::new(&port, 115200, Duration::from_millis(100))?;
+
+    for &id in &ids {
+        controller.disable_torque(id)?;
+    }
+    println!("Torque off on {:?}. Pose the arm, then press Enter.", ids);
+    io::stdin().read_line(&mut String::new())?;
+
+    for &id in &ids {
+        let position = controller.hold_position(id, None, None)?;
+        println!("servo {} holding at {}", id, position.as_ticks());
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+//! Pings servos from a smol executor, without tokio.
+//!
+//! Usage: smol_ping PORT [ID...]
+
+use std::{env, error::Error, process, time::Duration};
+
+use lx16a::AsyncServoController;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let Some(port) = args.next() else {
+        eprintln!("usage: smol_ping PORT [ID...]");
+        process::exit(2);
+    };
+    let mut ids = args.map(|id| id.parse()).collect::<Result<Vec<u8>, _>>()?;
+    if ids.is_empty() {
+        ids.push(1);
+    }
+
+    smol::block_on(async {
+        let controller = AsyncServoController::open_async_io(&port, 115200, Duration::from_millis(100))?;
+        for id in ids {
+            if controller.ping(id, None).await? {
+                let position = controller.get_position(id, None).await?;
+                println!("servo {}  position {:4} ({:6.1}°)", id, position.as_ticks(), position.as_degrees());
+            } else {
+                println!("servo {}  no answer", id);
+            }
+        }
+        Ok::<_, Box<dyn Error>>(())
+    })
+}
@@ -0,0 +1,35 @@
+//! Sweeps a servo from 0 to 1000 and back, printing telemetry at every step.
+//!
+//! Usage: sweep PORT [ID] [STEP]
+
+use std::{env, error::Error, process, time::Duration};
+
+use lx16a::ServoController;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let Some(port) = args.next() else {
+        eprintln!("usage: sweep PORT [ID] [STEP]");
+        process::exit(2);
+    };
+    let id: u8 = args.next().map(|id| id.parse()).transpose()?.unwrap_or(1);
+    let step: u16 = args.next().map(|step| step.parse()).transpose()?.unwrap_or(100);
+    if step == 0 {
+        return Err("STEP must be above 0".into());
+    }
+
+    let controller = ServoController::new(&port, 115200, Duration::from_millis(100))?;
+    let up: Vec<u16> = (0..=1000).step_by(step as usize).collect();
+    let down = up.iter().rev().skip(1);
+    let time = 20 * step.min(1000);
+
+    for &target in up.iter().chain(down) {
+        controller.move_and_wait(id, target, time, 10, Duration::from_millis(500))?;
+        let position = controller.get_position(id, None)?;
+        let temperature = controller.get_temperature(id, None)?;
+        let voltage = controller.get_voltage(id, None)?;
+        println!("target {:4}  position {:4} ({:6.1}°)  {}  {}",
+            target, position.as_ticks(), position.as_degrees(), temperature, voltage);
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Arbitrary bytes through `FrameReader`, handed out a few at a time.
+//!
+//! The first input byte picks the chunk size and reader mode; the rest is the stream.
+//! Run with `cargo fuzz run frame_reader` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lx16a::protocol::{Error, Frame, FrameReader, MAX_FRAME_LEN};
+use lx16a::Transport;
+
+// Serves `data` at most `chunk` bytes per call, then fails like a timed out port.
+// `read_some` may return fewer than `min` bytes, as a careless transport would.
+struct Chunked<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl Chunked<'_> {
+    fn take(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.chunk.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        n
+    }
+}
+
+impl Transport for Chunked<'_> {
+    type Error = ();
+
+    fn write_all(&mut self, _bytes: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ()> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.take(&mut buf[filled..]) {
+                0 => return Err(()),
+                n => filled += n,
+            }
+        }
+        Ok(())
+    }
+
+    fn read_some(&mut self, buf: &mut [u8], _min: usize) -> Result<usize, ()> {
+        match self.take(buf) {
+            0 => Err(()),
+            n => Ok(n),
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&config, stream)) = data.split_first() else { return };
+    let _ = Frame::decode(stream);
+
+    let mut reader = if config & 0x10 != 0 { FrameReader::single_shot() } else { FrameReader::new() };
+    let mut transport = Chunked { data: stream, chunk: (config & 0x0f) as usize + 1 };
+
+    // Every frame returned, good or bad, consumes at least a minimal frame of input
+    for _ in 0..=stream.len() / 6 + 1 {
+        match reader.read_frame(&mut transport) {
+            Ok(frame) => {
+                let mut buf = [0u8; MAX_FRAME_LEN];
+                assert_eq!(Frame::decode(frame.encode(&mut buf)), Ok(frame));
+            }
+            Err(Error::Frame { .. }) => {}
+            Err(Error::Transport(())) => return,
+        }
+    }
+    panic!("reader kept returning frames after its input ran out");
+});
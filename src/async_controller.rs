@@ -0,0 +1,832 @@
+//! Non-blocking driver for async applications, on any executor.
+//!
+//! The controller only needs `futures-io` streams; opening a serial port is up to a
+//! runtime-specific feature: `tokio-serial` for tokio, `async-serial` for smol and
+//! async-std (Unix only).
+
+use std::{future::Future, io, sync::Arc, time::{Duration, Instant}};
+
+use async_lock::Mutex;
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_lite::{AsyncReadExt, AsyncWriteExt, FutureExt};
+use futures_timer::Delay;
+use log::{debug, trace};
+
+use crate::builder::{SoftLimit, SoftLimitPolicy, TorqueCheck};
+use crate::controller::{
+    angle_limit_params, check_id, check_position, clamp_move_time, check_readable, decode_position, decode_u16, decode_u8, unexpected,
+    wire_position, word_at, MoveChecks, PING_TIMEOUT,
+};
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::protocol::*;
+use crate::types::{AngleLimit, Mode, ServoFault, ServoStatus};
+use crate::units::{Celsius, Millivolts, Position};
+
+/// A `futures-io` byte stream the async controller can drive. Tokio streams need the
+/// `tokio-util` compat wrapper; see [`AsyncServoController::from_tokio`].
+pub trait AsyncTransport: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncTransport for T {}
+
+/// Transport type the async controller drives.
+pub type BoxedAsyncTransport = Box<dyn AsyncTransport>;
+
+// How long the line must stay quiet before a resync counts as done; a reply
+// takes well under this at 115200 baud
+const RESYNC_QUIET: Duration = Duration::from_millis(2);
+
+// A query whose reply may still be on its way
+struct InFlight {
+    generation: u64,
+    servo_id: u8,
+    command: u8,
+    deadline: Instant,
+}
+
+struct Link {
+    transport: BoxedAsyncTransport,
+    // Keeps a frame read partway when its future is dropped; cleared by resync
+    reader: FrameReader,
+    // Set while a transaction is in flight, so one that timed out, failed or
+    // was cancelled leaves it set for the next to clean up after
+    resync: bool,
+    // Likewise for queries, naming the reply to throw away
+    in_flight: Option<InFlight>,
+    // Numbers queries, to tell abandoned ones apart in the log
+    generation: u64,
+}
+
+impl Link {
+    fn begin_query(&mut self, servo_id: u8, command: u8, deadline: Instant) -> u64 {
+        self.generation += 1;
+        self.resync = true;
+        self.in_flight = Some(InFlight { generation: self.generation, servo_id, command, deadline });
+        self.generation
+    }
+
+    fn finish_query(&mut self, generation: u64) {
+        if self.in_flight.as_ref().is_some_and(|query| query.generation == generation) {
+            self.in_flight = None;
+            self.resync = false;
+        }
+    }
+
+    // Discards whatever is left of an abandoned transaction, late replies included.
+    // The reply to an abandoned query is waited for until that query's own deadline,
+    // since one arriving later than the quiet period would otherwise pass for the
+    // answer to the same question asked again. Cancelling this leaves the marker set.
+    async fn resync(&mut self) {
+        if let Some(query) = &self.in_flight {
+            let (generation, servo_id, command) = (query.generation, query.servo_id, query.command);
+            match until(query.deadline, receive(&mut self.reader, self.transport.as_mut(), servo_id, command)).await {
+                Some(Ok(frame)) => debug!("Discarded late reply {:?} to abandoned query #{}", frame, generation),
+                Some(Err(e)) => debug!("Abandoned query #{} to servo {} left: {}", generation, servo_id, e),
+                None => debug!("Abandoned query #{} to servo {} was never answered", generation, servo_id),
+            }
+            self.in_flight = None;
+        }
+        if !self.resync {
+            return;
+        }
+        self.reader.clear();
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        let mut discarded = 0;
+        while let Some(Ok(n)) = timeout(RESYNC_QUIET, self.transport.read(&mut buf)).await {
+            if n == 0 {
+                break;
+            }
+            discarded += n;
+        }
+        if discarded > 0 {
+            debug!("Discarded {} stale bytes", discarded);
+        }
+        self.resync = false;
+    }
+}
+
+/// Async counterpart of [`ServoController`](crate::ServoController), with the same
+/// methods as `async fn`s and the same errors.
+///
+/// Every query holds the bus for its write and reply, so tasks sharing clones of one
+/// controller never interleave packets. Broadcast reads are refused up front, as in the
+/// blocking controller.
+///
+/// Query timeouts are enforced with a runtime-independent timer, not the serial port's
+/// own timeout, and cover waiting for the bus as well as the reply. A request that has started
+/// writing always finishes or fails the write before giving up. After a query times out,
+/// fails or has its future dropped, the next transaction first waits out that query's
+/// reply, up to the query's deadline, and discards any input left over, so a late reply
+/// is never taken for the answer to a newer question. Dropping futures, e.g. in a
+/// `select!`, is safe.
+///
+/// Soft limits and the torque check work as on the blocking controller, and clones share them.
+#[derive(Clone)]
+pub struct AsyncServoController {
+    serial: Arc<Mutex<Link>>,
+    checks: Arc<MoveChecks>,
+    timeout: Duration,
+}
+
+impl AsyncServoController
+{
+    /// Opens `port_name` through `tokio-serial`, for use on a tokio runtime. `timeout` is
+    /// the default for queries.
+    #[cfg(feature = "tokio-serial")]
+    pub fn new(port_name: &str, baud_rate: u32, timeout: Duration) -> Result<Self, ControllerError> {
+        let port = tokio_serial::SerialStream::open(&tokio_serial::new(port_name, baud_rate))
+            .map_err(|source| ControllerError::Open { port: port_name.to_string(), source })?;
+        Ok(Self::from_tokio(port, timeout))
+    }
+
+    /// Builds a controller over a tokio stream.
+    #[cfg(feature = "tokio-serial")]
+    pub fn from_tokio(transport: impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static, timeout: Duration) -> Self {
+        use tokio_util::compat::TokioAsyncReadCompatExt;
+        Self::with_transport(Box::new(transport.compat()), timeout)
+    }
+
+    /// Opens `port_name` through `async-io`, the reactor behind smol and async-std.
+    /// `timeout` is the default for queries.
+    #[cfg(all(feature = "async-serial", unix))]
+    pub fn open_async_io(port_name: &str, baud_rate: u32, timeout: Duration) -> Result<Self, ControllerError> {
+        use std::os::fd::{FromRawFd, IntoRawFd};
+
+        let port = serialport::new(port_name, baud_rate).open_native()
+            .map_err(|source| ControllerError::Open { port: port_name.to_string(), source })?;
+        // The port's own reads block in poll(); a plain file on the same descriptor lets
+        // async-io drive it non-blocking.
+        // SAFETY: into_raw_fd hands over sole ownership of an open descriptor.
+        let file = unsafe { std::fs::File::from_raw_fd(port.into_raw_fd()) };
+        let stream = async_io::Async::new(file)
+            .map_err(|source| ControllerError::Port { port: port_name.to_string(), source })?;
+        Ok(Self::with_transport(Box::new(stream), timeout))
+    }
+
+    /// Builds a controller over an already opened stream.
+    pub fn with_transport(transport: BoxedAsyncTransport, timeout: Duration) -> Self {
+        let link = Link { transport, reader: FrameReader::new(), resync: false, in_flight: None, generation: 0 };
+        AsyncServoController { serial: Arc::new(Mutex::new(link)), checks: Arc::new(MoveChecks::default()), timeout }
+    }
+
+    pub fn timeout(&self) -> Duration
+    {
+        self.timeout
+    }
+
+    /// Enables or disables checking moves against the last known torque state; see
+    /// [`ServoController::set_torque_check`](crate::ServoController::set_torque_check).
+    pub fn set_torque_check(&self, check: TorqueCheck)
+    {
+        self.checks.set_torque_check(check);
+    }
+
+    /// Restricts the positions `servo_id` may be commanded to, in servo ticks; see
+    /// [`ServoController::set_soft_limits`](crate::ServoController::set_soft_limits).
+    pub fn set_soft_limits(&self, servo_id: impl Into<ServoId>, min: impl Into<Position>, max: impl Into<Position>, policy: SoftLimitPolicy) -> Result<(), ControllerError>
+    {
+        self.checks.set_soft_limits(servo_id.into(), min.into(), max.into(), policy)
+    }
+
+    pub fn clear_soft_limits(&self, servo_id: impl Into<ServoId>)
+    {
+        self.checks.clear_soft_limits(servo_id.into());
+    }
+
+    pub fn soft_limits(&self, servo_id: impl Into<ServoId>) -> Option<SoftLimit>
+    {
+        self.checks.soft_limits(servo_id.into())
+    }
+
+    async fn command(&self, servo_id: ServoId, command: u8, params: &[u8]) -> Result<(), ControllerError>
+    {
+        check_id(servo_id)?;
+        let mut link = self.serial.lock().await;
+        link.resync().await;
+        link.resync = true;
+        send(link.transport.as_mut(), servo_id.into(), command, params).await?;
+        link.resync = false;
+        Ok(())
+    }
+
+    // The write and the reply under one lock. The deadline covers waiting for the lock
+    // and the reply, but not the write, which is never abandoned half sent.
+    async fn query(&self, servo_id: ServoId, command: u8, timeout: Option<Duration>) -> Result<Frame, ControllerError>
+    {
+        check_readable(servo_id)?;
+        let deadline = Instant::now() + timeout.unwrap_or(self.timeout);
+        let mut link = until(deadline, self.serial.lock()).await
+            .ok_or(ControllerError::Timeout)?;
+        link.resync().await;
+
+        let generation = link.begin_query(servo_id.into(), command, deadline);
+        send(link.transport.as_mut(), servo_id.into(), command, &[]).await?;
+        let link = &mut *link;
+        let frame = until(deadline, receive(&mut link.reader, link.transport.as_mut(), servo_id.into(), command)).await
+            .ok_or(ControllerError::Timeout)??;
+        link.finish_query(generation);
+        Ok(frame)
+    }
+
+    /// Moves to `position` over `time` ms. Plain `u16` positions are raw ticks. As with
+    /// [`ServoController::move_servo`], 0 is as fast as possible and times above 30000 are clamped.
+    pub async fn move_servo(&self, servo_id: impl Into<ServoId>, position: impl Into<Position>, time: u16) -> Result<(), ControllerError>
+    {
+        let servo_id = servo_id.into();
+        self.move_servo_raw(servo_id, wire_position(position.into())?, time).await
+    }
+
+    pub async fn move_servo_raw(&self, servo_id: impl Into<ServoId>, position: u16, time: u16) -> Result<(), ControllerError>
+    {
+        let servo_id = servo_id.into();
+        check_position(position)?;
+        self.checks.check_torque(servo_id)?;
+        let position = self.checks.apply_soft_limits(servo_id, position)?;
+        let time = clamp_move_time(time);
+        self.command(servo_id, SERVO_MOVE_TIME_WRITE, &[lower_byte(position), higher_byte(position), lower_byte(time), higher_byte(time)]).await
+    }
+
+    /// Stages a move that starts on [`move_start`](Self::move_start).
+    pub async fn move_prepare(&self, servo_id: impl Into<ServoId>, position: impl Into<Position>, time: u16) -> Result<(), ControllerError>
+    {
+        let servo_id = servo_id.into();
+        let position = wire_position(position.into())?;
+        self.checks.check_torque(servo_id)?;
+        let position = self.checks.apply_soft_limits(servo_id, position)?;
+        let time = clamp_move_time(time);
+        self.command(servo_id, SERVO_MOVE_TIME_WAIT_WRITE, &[lower_byte(position), higher_byte(position), lower_byte(time), higher_byte(time)]).await
+    }
+
+    pub async fn move_start(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError>
+    {
+        self.command(servo_id.into(), SERVO_MOVE_START, &[]).await
+    }
+
+    pub async fn move_stop(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError>
+    {
+        self.command(servo_id.into(), SERVO_MOVE_STOP, &[]).await
+    }
+
+    /// Switches between position and motor mode. Motor speeds outside -1000..=1000 are rejected.
+    pub async fn set_mode(&self, servo_id: impl Into<ServoId>, mode: Mode) -> Result<(), ControllerError>
+    {
+        let servo_id = servo_id.into();
+        if !mode.is_valid() {
+            return Err(ControllerError::InvalidParameter(format!("{:?} speed outside -1000..=1000", mode)));
+        }
+        self.command(servo_id, SERVO_OR_MOTOR_MODE_WRITE, &mode.to_params()).await
+    }
+
+    pub async fn get_mode(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<Mode, ControllerError>
+    {
+        let response = self.query(servo_id.into(), SERVO_OR_MOTOR_MODE_READ, timeout).await?;
+        Mode::from_params(response.params()).ok_or_else(|| unexpected(&response))
+    }
+
+    /// Loads (`true`) or unloads the motor.
+    pub async fn set_torque(&self, servo_id: impl Into<ServoId>, enabled: bool) -> Result<(), ControllerError>
+    {
+        let servo_id = servo_id.into();
+        self.command(servo_id, SERVO_LOAD_OR_UNLOAD_WRITE, &[enabled as u8]).await?;
+        self.checks.note_torque(servo_id, enabled);
+        Ok(())
+    }
+
+    pub async fn is_torque_enabled(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<bool, ControllerError>
+    {
+        let servo_id = servo_id.into();
+        let enabled = decode_u8(&self.query(servo_id, SERVO_LOAD_OR_UNLOAD_READ, timeout).await?)? != 0;
+        self.checks.note_torque(servo_id, enabled);
+        Ok(enabled)
+    }
+
+    pub async fn led_on(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError>
+    {
+        self.command(servo_id.into(), SERVO_LED_CTRL_WRITE, &[1]).await
+    }
+
+    pub async fn led_off(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError>
+    {
+        self.command(servo_id.into(), SERVO_LED_CTRL_WRITE, &[0]).await
+    }
+
+    /// Writes the hardware travel limits (EEPROM). `min` must be below `max`, both in 0..=1000.
+    pub async fn set_angle_limit(&self, servo_id: impl Into<ServoId>, min: impl Into<Position>, max: impl Into<Position>) -> Result<(), ControllerError>
+    {
+        let params = angle_limit_params(min.into(), max.into())?;
+        self.command(servo_id.into(), SERVO_ANGLE_LIMIT_WRITE, &params).await
+    }
+
+    pub async fn read_angle_limit(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<AngleLimit, ControllerError>
+    {
+        let response = self.query(servo_id.into(), SERVO_ANGLE_LIMIT_READ, timeout).await?;
+        Ok(AngleLimit {
+            min: Position::from_wire(word_at(&response, 0)?),
+            max: Position::from_wire(word_at(&response, 2)?),
+        })
+    }
+
+    /// Current position; see [`ServoController::get_position`](crate::ServoController::get_position).
+    pub async fn get_position(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<Position, ControllerError>
+    {
+        decode_position(&self.query(servo_id.into(), SERVO_POS_READ, timeout).await?)
+    }
+
+    pub async fn get_temperature(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<Celsius, ControllerError>
+    {
+        Ok(Celsius(decode_u8(&self.query(servo_id.into(), SERVO_TEMP_READ, timeout).await?)?))
+    }
+
+    pub async fn get_voltage(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<Millivolts, ControllerError>
+    {
+        Ok(Millivolts(decode_u16(&self.query(servo_id.into(), SERVO_VIN_READ, timeout).await?)?))
+    }
+
+    pub async fn read_faults(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<ServoFault, ControllerError>
+    {
+        Ok(ServoFault::from_bits_truncate(decode_u8(&self.query(servo_id.into(), SERVO_LED_ERROR_READ, timeout).await?)?))
+    }
+
+    /// Reads position, temperature, voltage, faults, mode and torque state.
+    ///
+    /// Unlike the blocking version each query takes the bus separately, so other tasks
+    /// can get in between. Failed queries leave their field `None`; fails only when
+    /// nothing answered.
+    pub async fn get_status(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<ServoStatus, ControllerError>
+    {
+        let servo_id = servo_id.into();
+        check_readable(servo_id)?;
+
+        let mut last_error = None;
+        let mut answered = false;
+        let mut status = ServoStatus { servo_id, position: None, temperature: None, voltage: None, faults: None, mode: None, torque_enabled: None };
+        for command in [SERVO_POS_READ, SERVO_TEMP_READ, SERVO_VIN_READ, SERVO_LED_ERROR_READ, SERVO_OR_MOTOR_MODE_READ, SERVO_LOAD_OR_UNLOAD_READ] {
+            let frame = match self.query(servo_id, command, timeout).await {
+                Ok(frame) => {
+                    answered = true;
+                    frame
+                }
+                Err(e) => {
+                    debug!("Status query {} for servo {} failed: {}", command, servo_id, e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            match command {
+                SERVO_POS_READ => status.position = decode_position(&frame).ok(),
+                SERVO_TEMP_READ => status.temperature = decode_u8(&frame).ok().map(Celsius),
+                SERVO_VIN_READ => status.voltage = decode_u16(&frame).ok().map(Millivolts),
+                SERVO_LED_ERROR_READ => status.faults = decode_u8(&frame).ok().map(ServoFault::from_bits_truncate),
+                SERVO_OR_MOTOR_MODE_READ => status.mode = Mode::from_params(frame.params()),
+                _ => status.torque_enabled = decode_u8(&frame).ok().map(|v| v != 0),
+            }
+        }
+
+        match last_error {
+            Some(e) if !answered => Err(e),
+            _ => Ok(status),
+        }
+    }
+
+    /// `true` if `servo_id` answers an id read within `timeout` (default 50 ms).
+    pub async fn ping(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<bool, ControllerError>
+    {
+        let servo_id = servo_id.into();
+        match self.query(servo_id, SERVO_ID_READ, Some(timeout.unwrap_or(PING_TIMEOUT))).await {
+            Ok(_) => Ok(true),
+            Err(e @ (ControllerError::Timeout | ControllerError::InvalidFrame { .. } | ControllerError::UnexpectedResponse { .. })) => {
+                debug!("No answer to ping from servo {}: {}", servo_id, e);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+// None if `future` doesn't finish within `duration`
+async fn timeout<T>(duration: Duration, future: impl Future<Output = T>) -> Option<T> {
+    async { Some(future.await) }
+        .or(async {
+            Delay::new(duration).await;
+            None
+        })
+        .await
+}
+
+async fn until<T>(deadline: Instant, future: impl Future<Output = T>) -> Option<T> {
+    timeout(deadline.saturating_duration_since(Instant::now()), future).await
+}
+
+async fn send(serial: &mut dyn AsyncTransport, servo_id: u8, command: u8, params: &[u8]) -> Result<(), ControllerError> {
+    let frame = Frame::new(servo_id, command, params)?;
+    let mut buf = [0u8; MAX_FRAME_LEN];
+    let packet = frame.encode(&mut buf);
+
+    trace!("TX {}", format_packet(packet));
+    serial.write_all(packet).await
+        .map_err(|source| ControllerError::Write { packet: packet.to_vec(), source })?;
+    serial.flush().await
+        .map_err(|source| ControllerError::Write { packet: packet.to_vec(), source })
+}
+
+// Skips frames meant for another query; the caller's timeout bounds the wait
+async fn receive(reader: &mut FrameReader, serial: &mut dyn AsyncTransport, servo_id: u8, command: u8) -> Result<Frame, ControllerError> {
+    loop
+    {
+        let frame = read_frame_async(reader, serial).await?;
+        trace!("RX {:?}", frame);
+        if frame.command == command && frame.servo_id == servo_id {
+            return Ok(frame);
+        }
+        debug!("Discarding response {:?} while waiting for servo {} command {}", frame, servo_id, command);
+    }
+}
+
+/// Reads one frame through `reader`, which resyncs after false headers exactly as
+/// [`FrameReader::read_frame`] does for the blocking controller. Never reads past the
+/// end of a frame.
+async fn read_frame_async(reader: &mut FrameReader, serial: &mut dyn AsyncTransport) -> Result<Frame, ControllerError> {
+    loop
+    {
+        match reader.step() {
+            Step::Need(len) => match serial.read(reader.unfilled(len)).await {
+                Ok(0) => {
+                    reader.clear();
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                Ok(read) => reader.advance(read),
+                Err(e) => {
+                    reader.clear();
+                    return Err(e.into());
+                }
+            },
+            Step::Frame(frame) => return Ok(frame),
+            Step::Invalid { error, raw } => return Err(ControllerError::InvalidFrame { error, frame: raw.as_bytes().to_vec() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::{HashMap, VecDeque}, future::Future, io, pin::Pin, sync::Arc, task::{Context, Poll, Waker}, time::{Duration, Instant}};
+
+    use futures_io::{AsyncRead, AsyncWrite};
+    use futures_lite::future;
+    use futures_timer::Delay;
+
+    use super::AsyncServoController;
+    use crate::builder::{SoftLimitPolicy, TorqueCheck};
+    use crate::error::ControllerError;
+    use crate::protocol::*;
+    use crate::units::{Celsius, Position};
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    // An in-memory serial line: records the frames written and answers each with the
+    // bytes scripted for its servo and command, after a delay. With nothing to read it
+    // stays pending, leaving it to the controller's own timeout.
+    #[derive(Clone, Default)]
+    struct Pipe {
+        state: Arc<std::sync::Mutex<PipeState>>,
+    }
+
+    // Bytes sent back for a write, and how long after it
+    type Reply = (Duration, Vec<u8>);
+
+    #[derive(Default)]
+    struct PipeState {
+        written: Vec<Frame>,
+        replies: HashMap<(u8, u8), VecDeque<Reply>>,
+        // Replies in the order they become readable
+        input: VecDeque<(Instant, Vec<u8>)>,
+        timer: Option<Delay>,
+        reader: Option<Waker>,
+    }
+
+    impl Pipe {
+        fn controller(&self) -> AsyncServoController {
+            AsyncServoController::with_transport(Box::new(self.clone()), TIMEOUT)
+        }
+
+        fn reply_to(&self, servo_id: u8, command: u8, params: &[u8], delay: Duration) {
+            let mut buf = [0u8; MAX_FRAME_LEN];
+            let frame = Frame::new(servo_id, command, params).unwrap();
+            self.reply_bytes(servo_id, command, frame.encode(&mut buf), delay);
+        }
+
+        fn reply_bytes(&self, servo_id: u8, command: u8, bytes: &[u8], delay: Duration) {
+            let mut state = self.state.lock().unwrap();
+            state.replies.entry((servo_id, command)).or_default().push_back((delay, bytes.to_vec()));
+        }
+
+        fn written(&self) -> Vec<Frame> {
+            self.state.lock().unwrap().written.clone()
+        }
+    }
+
+    impl AsyncWrite for Pipe {
+        fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            let mut state = self.state.lock().unwrap();
+            let frame = Frame::decode(buf).expect("the controller writes whole frames");
+            if let Some((delay, bytes)) = state.replies.get_mut(&(frame.servo_id, frame.command)).and_then(VecDeque::pop_front) {
+                let at = Instant::now() + delay;
+                let index = state.input.iter().position(|(ready, _)| *ready > at).unwrap_or(state.input.len());
+                state.input.insert(index, (at, bytes));
+                state.timer = None;
+                if let Some(reader) = state.reader.take() {
+                    reader.wake();
+                }
+            }
+            state.written.push(frame);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncRead for Pipe {
+        fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+            loop {
+                let Some(&(ready, _)) = state.input.front() else {
+                    state.reader = Some(cx.waker().clone());
+                    return Poll::Pending;
+                };
+                let now = Instant::now();
+                if ready > now {
+                    let timer = state.timer.get_or_insert_with(|| Delay::new(ready - now));
+                    match Pin::new(timer).poll(cx) {
+                        Poll::Pending => return Poll::Pending,
+                        Poll::Ready(()) => {
+                            state.timer = None;
+                            continue;
+                        }
+                    }
+                }
+
+                let bytes = &mut state.input.front_mut().unwrap().1;
+                let n = bytes.len().min(buf.len());
+                buf[..n].copy_from_slice(&bytes[..n]);
+                bytes.drain(..n);
+                if bytes.is_empty() {
+                    state.input.pop_front();
+                }
+                return Poll::Ready(Ok(n));
+            }
+        }
+    }
+
+    fn position(ticks: i16) -> [u8; 2] {
+        ticks.to_le_bytes()
+    }
+
+    #[test]
+    fn get_position_parses_the_reply() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(500), Duration::ZERO);
+        let position = future::block_on(pipe.controller().get_position(1, None)).unwrap();
+        assert_eq!(position, Position::from(500u16));
+    }
+
+    #[test]
+    fn a_false_header_before_the_reply_is_skipped() {
+        let pipe = Pipe::default();
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        let reply = Frame::new(1, SERVO_POS_READ, &position(500)).unwrap();
+        let mut bytes = vec![FRAME_HEADER, FRAME_HEADER];
+        bytes.extend_from_slice(reply.encode(&mut buf));
+        pipe.reply_bytes(1, SERVO_POS_READ, &bytes, Duration::ZERO);
+
+        let position = future::block_on(pipe.controller().get_position(1, None)).unwrap();
+        assert_eq!(position, Position::from(500u16));
+    }
+
+    #[test]
+    fn concurrent_queries_each_get_their_own_reply() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(100), Duration::from_millis(5));
+        pipe.reply_to(2, SERVO_POS_READ, &position(200), Duration::ZERO);
+        let controller = pipe.controller();
+        let (first, second) = future::block_on(future::zip(controller.get_position(1, None), controller.get_position(2, None)));
+        assert_eq!(first.unwrap(), Position::from(100u16));
+        assert_eq!(second.unwrap(), Position::from(200u16));
+        assert_eq!(pipe.written().len(), 2);
+    }
+
+    #[test]
+    fn moves_are_held_to_the_soft_limits() {
+        let pipe = Pipe::default();
+        let controller = pipe.controller();
+        controller.set_soft_limits(1, 200u16, 800u16, SoftLimitPolicy::Clamp).unwrap();
+        future::block_on(controller.move_servo(1, 900u16, 0)).unwrap();
+        future::block_on(controller.move_prepare(1, 100u16, 0)).unwrap();
+        let params: Vec<_> = pipe.written().iter().map(|frame| frame.params().to_vec()).collect();
+        assert_eq!(params, [[0x20, 0x03, 0, 0], [0xC8, 0x00, 0, 0]]);
+
+        controller.set_soft_limits(1, 200u16, 800u16, SoftLimitPolicy::Reject).unwrap();
+        let result = future::block_on(controller.move_servo(1, 900u16, 0));
+        assert!(matches!(result, Err(ControllerError::InvalidParameter(_))));
+        assert_eq!(pipe.written().len(), 2);
+    }
+
+    #[test]
+    fn move_times_above_30000_ms_are_clamped() {
+        let pipe = Pipe::default();
+        let controller = pipe.controller();
+        for time in [0, 30001, u16::MAX] {
+            future::block_on(controller.move_servo(1, 500u16, time)).unwrap();
+        }
+        future::block_on(controller.move_prepare(1, 500u16, 30001)).unwrap();
+        let times: Vec<u16> = pipe.written().iter().map(|frame| u16::from_le_bytes([frame.params()[2], frame.params()[3]])).collect();
+        assert_eq!(times, [0, 30000, 30000, 30000]);
+    }
+
+    #[test]
+    fn moves_to_an_unloaded_servo_fail_the_torque_check() {
+        let pipe = Pipe::default();
+        let controller = pipe.controller();
+        controller.set_torque_check(TorqueCheck::Error);
+        future::block_on(controller.set_torque(1, false)).unwrap();
+        let result = future::block_on(controller.move_servo(1, 500u16, 0));
+        assert!(matches!(result, Err(ControllerError::TorqueDisabled { .. })));
+
+        pipe.reply_to(1, SERVO_LOAD_OR_UNLOAD_READ, &[1], Duration::ZERO);
+        assert!(future::block_on(controller.is_torque_enabled(1, None)).unwrap());
+        future::block_on(controller.move_servo(1, 500u16, 0)).unwrap();
+        assert_eq!(pipe.written().last().map(|frame| frame.command), Some(SERVO_MOVE_TIME_WRITE));
+    }
+
+    #[test]
+    fn a_reply_within_the_deadline_is_waited_for() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(500), Duration::from_millis(30));
+        let position = future::block_on(pipe.controller().get_position(1, None)).unwrap();
+        assert_eq!(position, Position::from(500u16));
+    }
+
+    #[test]
+    fn a_reply_beyond_the_deadline_times_out_promptly() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(500), Duration::from_millis(300));
+        let start = Instant::now();
+        let result = future::block_on(pipe.controller().get_position(1, Some(Duration::from_millis(20))));
+        assert!(matches!(result, Err(ControllerError::Timeout)));
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(150), "took {:?}", elapsed);
+        // The query went out in full before giving up
+        assert_eq!(pipe.written().len(), 1);
+    }
+
+    #[test]
+    fn a_late_reply_is_discarded_by_the_next_query() {
+        let pipe = Pipe::default();
+        let controller = pipe.controller();
+        pipe.reply_to(1, SERVO_POS_READ, &position(500), Duration::from_millis(20));
+        let result = future::block_on(controller.get_position(1, Some(Duration::from_millis(10))));
+        assert!(matches!(result, Err(ControllerError::Timeout)));
+
+        // The late reply is waiting to be read when the same question is asked again
+        future::block_on(Delay::new(Duration::from_millis(30)));
+        pipe.reply_to(1, SERVO_POS_READ, &position(600), Duration::ZERO);
+        let position = future::block_on(controller.get_position(1, None)).unwrap();
+        assert_eq!(position, Position::from(600u16));
+    }
+
+    #[test]
+    fn the_deadline_covers_waiting_for_the_bus() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(100), Duration::from_millis(40));
+        let controller = pipe.controller();
+        let waiting = async {
+            // Give the first query the bus
+            Delay::new(Duration::from_millis(5)).await;
+            let start = Instant::now();
+            (controller.get_position(2, Some(Duration::from_millis(10))).await, start.elapsed())
+        };
+        let (first, (second, waited)) = future::block_on(future::zip(controller.get_position(1, None), waiting));
+        assert_eq!(first.unwrap(), Position::from(100u16));
+        assert!(matches!(second, Err(ControllerError::Timeout)));
+        assert!(waited < Duration::from_millis(30), "waited {:?}", waited);
+        // The second query never got to write
+        assert_eq!(pipe.written().len(), 1);
+    }
+
+    // `query`, given up on after `after` the way a select! timeout drops it
+    async fn cancel_after<T>(after: Duration, query: impl Future<Output = Result<T, ControllerError>>) -> Option<Result<T, ControllerError>> {
+        future::or(async { Some(query.await) }, async {
+            Delay::new(after).await;
+            None
+        }).await
+    }
+
+    #[test]
+    fn the_late_reply_to_a_cancelled_query_is_not_the_answer_to_the_same_question() {
+        let pipe = Pipe::default();
+        pipe.reply_to(1, SERVO_POS_READ, &position(100), Duration::from_millis(20));
+        pipe.reply_to(1, SERVO_POS_READ, &position(200), Duration::ZERO);
+        let controller = pipe.controller();
+        assert!(future::block_on(cancel_after(Duration::from_millis(5), controller.get_position(1, None))).is_none());
+
+        let position = future::block_on(controller.get_position(1, None)).unwrap();
+        assert_eq!(position, Position::from(200u16));
+        assert_eq!(pipe.written().len(), 2);
+    }
+
+    #[test]
+    fn a_query_racing_a_cancelled_one_never_gets_its_payload() {
+        let pipe = Pipe::default();
+        // The stale reply is the same command as the next query; only the id tells them apart
+        pipe.reply_to(1, SERVO_POS_READ, &position(100), Duration::from_millis(20));
+        pipe.reply_to(2, SERVO_POS_READ, &position(200), Duration::from_millis(5));
+        pipe.reply_to(1, SERVO_TEMP_READ, &[40], Duration::ZERO);
+        let controller = pipe.controller();
+
+        let cancelled = cancel_after(Duration::from_millis(5), controller.get_position(1, None));
+        let waiting = async {
+            // Queue up behind the first query while it still holds the bus
+            Delay::new(Duration::from_millis(1)).await;
+            controller.get_position(2, None).await
+        };
+        let (cancelled, second) = future::block_on(future::zip(cancelled, waiting));
+        assert!(cancelled.is_none());
+        assert_eq!(second.unwrap(), Position::from(200u16));
+
+        // Same servo, a different question
+        let temperature = future::block_on(controller.get_temperature(1, None)).unwrap();
+        assert_eq!(temperature, Celsius(40));
+        let servos: Vec<(u8, u8)> = pipe.written().iter().map(|frame| (frame.servo_id, frame.command)).collect();
+        assert_eq!(servos, [(1, SERVO_POS_READ), (2, SERVO_POS_READ), (1, SERVO_TEMP_READ)]);
+    }
+
+    #[test]
+    fn a_cancelled_query_never_answered_holds_the_next_one_only_until_its_deadline() {
+        let pipe = Pipe::default();
+        pipe.reply_to(2, SERVO_POS_READ, &position(200), Duration::ZERO);
+        let controller = pipe.controller();
+        let begun = Instant::now();
+        assert!(future::block_on(cancel_after(Duration::from_millis(5), controller.get_position(1, Some(Duration::from_millis(20))))).is_none());
+
+        let position = future::block_on(controller.get_position(2, None)).unwrap();
+        assert_eq!(position, Position::from(200u16));
+        let elapsed = begun.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < TIMEOUT, "took {:?}", elapsed);
+    }
+
+    // A servo bus at the far end of a real socket pair, driven by smol's reactor: answers
+    // position and id reads from `present` servos, and records the rest
+    #[cfg(unix)]
+    async fn simulated_bus(mut line: smol::net::unix::UnixStream, present: &[u8]) -> Vec<Frame> {
+        use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        while line.read_exact(&mut buf[..4]).await.is_ok() {
+            let len = usize::from(buf[3]) + 3;
+            line.read_exact(&mut buf[4..len]).await.unwrap();
+            let frame = Frame::decode(&buf[..len]).unwrap();
+            received.push(frame);
+            let params = match frame.command {
+                SERVO_POS_READ => position(frame.servo_id as i16 * 100).to_vec(),
+                SERVO_ID_READ => vec![frame.servo_id],
+                _ => continue,
+            };
+            if present.contains(&frame.servo_id) {
+                let mut reply = [0u8; MAX_FRAME_LEN];
+                line.write_all(Frame::new(frame.servo_id, frame.command, &params).unwrap().encode(&mut reply)).await.unwrap();
+            }
+        }
+        received
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_controller_runs_on_smol_over_a_duplex_pipe() {
+        smol::block_on(async {
+            let (near, far) = smol::net::unix::UnixStream::pair().unwrap();
+            let bus = smol::spawn(async move { simulated_bus(far, &[1, 2, 3]).await });
+            let controller = AsyncServoController::with_transport(Box::new(near), TIMEOUT);
+
+            let (first, (second, third)) = future::zip(
+                controller.get_position(1, None),
+                future::zip(controller.get_position(2, None), controller.get_position(3, None)),
+            ).await;
+            assert_eq!([first.unwrap(), second.unwrap(), third.unwrap()], [100u16, 200, 300].map(Position::from));
+            assert!(controller.ping(2, None).await.unwrap());
+            assert!(!controller.ping(9, None).await.unwrap());
+            controller.move_servo(1, 500u16, 1000).await.unwrap();
+            drop(controller);
+
+            let received = bus.await;
+            assert_eq!(received.len(), 6);
+            assert_eq!(received[5].command, SERVO_MOVE_TIME_WRITE);
+            assert_eq!(received[5].params(), [0xF4, 0x01, 0xE8, 0x03]);
+        });
+    }
+}
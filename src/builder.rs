@@ -0,0 +1,259 @@
+//! Configuring and opening a [`ServoController`] on a serial port.
+
+use std::time::Duration;
+
+use crate::controller::{ServoController, DEFAULT_MAX_WRITE_LEN};
+use crate::error::ControllerError;
+use crate::units::Position;
+
+/// How a controller recovers when its serial port disappears, e.g. on a USB hub reset.
+///
+/// Reopening waits `backoff` before the first attempt and doubles it after each failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    pub max_attempts: u32,
+    pub backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    /// Five attempts starting at 200 ms, about 6 s in total.
+    fn default() -> Self {
+        ReconnectPolicy { max_attempts: 5, backoff: Duration::from_millis(200) }
+    }
+}
+
+/// How often a query is re-sent after a timeout, corrupted or unexpected reply.
+///
+/// Only reads are retried; writes are sent once, since repeating an EEPROM write or a
+/// relative command isn't harmless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RetryPolicy {
+    /// Extra attempts after the first; 0 disables retries.
+    pub retries: u32,
+    /// Pause between attempts, with the bus released.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    pub const fn new(retries: u32, delay: Duration) -> Self {
+        RetryPolicy { retries, delay }
+    }
+}
+
+/// Per-call overrides for a query; `None` fields use the controller's settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryOptions {
+    pub timeout: Option<Duration>,
+    pub retries: Option<RetryPolicy>,
+}
+
+/// Settings for [`ServoController::get_position_averaged`](crate::ServoController::get_position_averaged).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AveragingOptions {
+    /// Used for every sample.
+    pub query: QueryOptions,
+    /// Samples further than this many ticks from the median are dropped; `None` keeps all.
+    pub max_deviation: Option<u16>,
+    /// Fraction of samples allowed to fail before giving up, 0.0..=1.0.
+    pub max_failure_ratio: f32,
+}
+
+impl Default for AveragingOptions {
+    /// No outlier rejection; fails once more than half the samples have.
+    fn default() -> Self {
+        AveragingOptions { query: QueryOptions::default(), max_deviation: None, max_failure_ratio: 0.5 }
+    }
+}
+
+/// What the controller does when a move goes to a servo it believes is unloaded.
+///
+/// The belief comes from this controller's own torque writes and reads, so it is a
+/// best-effort hint: power cycles and other bus masters aren't seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TorqueCheck {
+    #[default]
+    Off,
+    /// Log a warning and send the move anyway.
+    Warn,
+    /// Fail with `ControllerError::TorqueDisabled` without sending.
+    Error,
+}
+
+/// What [`move_group`](crate::ServoController::move_group_with) does when some servos
+/// fail to stage their move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GroupFailurePolicy {
+    /// Start nothing. Servos that did stage keep the pending move until the next start.
+    #[default]
+    Abort,
+    /// Start the servos that staged, leaving the failed ones where they are.
+    StartStaged,
+}
+
+/// What happens to a move outside a servo's soft limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SoftLimitPolicy {
+    /// Log a warning and move to the nearest limit instead.
+    #[default]
+    Clamp,
+    /// Fail with `ControllerError::InvalidParameter` without sending.
+    Reject,
+}
+
+/// Range a servo may be commanded to, enforced by the controller on top of the angle
+/// limits stored in the servo; see [`ServoController::set_soft_limits`](crate::ServoController::set_soft_limits).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoftLimit {
+    pub min: Position,
+    pub max: Position,
+    pub policy: SoftLimitPolicy,
+}
+
+/// Broadcast sent when the last handle to a controller is dropped, including while
+/// unwinding from a panic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropAction {
+    /// Leave the servos as they are.
+    #[default]
+    Nothing,
+    /// `SERVO_MOVE_STOP`, halting moves in progress, then a switch to servo mode, which
+    /// stops servos in motor mode.
+    StopAll,
+    /// Unloads every motor, which also stops servos in motor mode.
+    UnloadAll,
+}
+
+/// Builder returned by [`ServoController::builder`].
+#[derive(Debug, Clone)]
+pub struct ServoControllerBuilder {
+    port_name: String,
+    baud_rate: u32,
+    timeout: Duration,
+    reconnect: Option<ReconnectPolicy>,
+    torque_check: TorqueCheck,
+    on_drop: DropAction,
+    retry: RetryPolicy,
+    response_delay: Duration,
+    verify_writes: bool,
+    exclusive: bool,
+    dry_run: bool,
+    single_shot_reads: bool,
+    max_write_len: usize,
+}
+
+impl ServoControllerBuilder {
+    pub(crate) fn new(port_name: &str) -> Self {
+        ServoControllerBuilder {
+            port_name: port_name.to_string(),
+            baud_rate: 115200,
+            timeout: Duration::from_millis(100),
+            reconnect: None,
+            torque_check: TorqueCheck::Off,
+            on_drop: DropAction::Nothing,
+            retry: RetryPolicy::default(),
+            response_delay: Duration::ZERO,
+            verify_writes: false,
+            exclusive: true,
+            dry_run: false,
+            single_shot_reads: false,
+            max_write_len: DEFAULT_MAX_WRITE_LEN,
+        }
+    }
+
+    /// Defaults to 115200, the LX-16A factory setting.
+    pub fn baud_rate(mut self, baud_rate: u32) -> Self {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Default query timeout, 100 ms unless set.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reopens the port after an I/O error that looks like it went away, and retries the
+    /// failed operation once. Settings the servos only keep in RAM aren't restored; watch
+    /// [`ServoController::reconnected_count`] to reapply them.
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Checks moves against the last known torque state; off by default.
+    pub fn torque_check(mut self, check: TorqueCheck) -> Self {
+        self.torque_check = check;
+        self
+    }
+
+    /// What to broadcast when the controller goes away; nothing by default.
+    pub fn on_drop(mut self, action: DropAction) -> Self {
+        self.on_drop = action;
+        self
+    }
+
+    /// Retries for every query; none by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Pause between sending a query and reading the reply, for adapters that lose the
+    /// first reply byte when read too early. Zero by default.
+    pub fn response_delay(mut self, delay: Duration) -> Self {
+        self.response_delay = delay;
+        self
+    }
+
+    /// Reads settings back after writing them; see [`ServoController::with_verify_writes`].
+    pub fn verify_writes(mut self, verify: bool) -> Self {
+        self.verify_writes = verify;
+        self
+    }
+
+    /// Locks the port against other openers, so a second process fails to open it instead
+    /// of stealing replies. On by default; only has an effect on Unix, where ports are
+    /// otherwise shared (Windows never shares COM ports).
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = exclusive;
+        self
+    }
+
+    /// Opens the port but never writes to it: packets are encoded and logged at debug
+    /// level, then dropped, and nothing is sent on drop either.
+    ///
+    /// Reads don't touch the bus and return placeholder values, all zero: position 0,
+    /// 0 mV, 0 °C, position mode, torque off. Waiting for a move therefore times out
+    /// unless the target is 0. Useful for checking a sequence of commands for argument
+    /// errors without moving anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Reads replies in as few calls as possible instead of header, length and body
+    /// separately; see [`ServoController::set_single_shot_reads`]. Off by default.
+    pub fn single_shot_reads(mut self, single_shot: bool) -> Self {
+        self.single_shot_reads = single_shot;
+        self
+    }
+
+    /// Longest single write for multi-servo moves; see [`ServoController::set_max_write_len`].
+    pub fn max_write_len(mut self, len: usize) -> Self {
+        self.max_write_len = len;
+        self
+    }
+
+    pub fn build(self) -> Result<ServoController, ControllerError> {
+        let controller = ServoController::open(&self.port_name, self.baud_rate, self.timeout, self.reconnect, self.exclusive)?
+            .with_verify_writes(self.verify_writes);
+        controller.set_torque_check(self.torque_check);
+        controller.set_drop_action(self.on_drop);
+        controller.set_retry_policy(self.retry);
+        controller.set_response_delay(self.response_delay);
+        controller.set_dry_run(self.dry_run);
+        controller.set_single_shot_reads(self.single_shot_reads);
+        controller.set_max_write_len(self.max_write_len);
+        Ok(controller)
+    }
+}
@@ -0,0 +1,84 @@
+//! Object-safe view of the controller for code that wants to swap in a fake.
+
+use std::time::Duration;
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::types::{AngleLimit, Mode, ServoStatus};
+use crate::units::{Celsius, Millivolts, Position};
+
+/// The primitive servo operations, implemented by [`ServoController`].
+///
+/// Application code can take `&dyn ServoBus` and be tested against its own
+/// implementation instead of hardware.
+pub trait ServoBus {
+    fn move_servo(&self, servo_id: ServoId, position: Position, time: u16) -> Result<(), ControllerError>;
+    fn move_prepare(&self, servo_id: ServoId, position: Position, time: u16) -> Result<(), ControllerError>;
+    fn move_start(&self, servo_id: ServoId) -> Result<(), ControllerError>;
+    fn move_stop(&self, servo_id: ServoId) -> Result<(), ControllerError>;
+    fn set_mode(&self, servo_id: ServoId, mode: Mode) -> Result<(), ControllerError>;
+    fn set_angle_limit(&self, servo_id: ServoId, min: Position, max: Position) -> Result<(), ControllerError>;
+
+    fn get_position(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Position, ControllerError>;
+    fn get_mode(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Mode, ControllerError>;
+    fn get_temperature(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Celsius, ControllerError>;
+    fn get_voltage(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Millivolts, ControllerError>;
+    fn get_status(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<ServoStatus, ControllerError>;
+    fn read_angle_limit(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<AngleLimit, ControllerError>;
+    fn ping(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<bool, ControllerError>;
+}
+
+impl ServoBus for ServoController {
+    fn move_servo(&self, servo_id: ServoId, position: Position, time: u16) -> Result<(), ControllerError> {
+        ServoController::move_servo(self, servo_id, position, time)
+    }
+
+    fn move_prepare(&self, servo_id: ServoId, position: Position, time: u16) -> Result<(), ControllerError> {
+        ServoController::move_prepare(self, servo_id, position, time)
+    }
+
+    fn move_start(&self, servo_id: ServoId) -> Result<(), ControllerError> {
+        ServoController::move_start(self, servo_id)
+    }
+
+    fn move_stop(&self, servo_id: ServoId) -> Result<(), ControllerError> {
+        ServoController::move_stop(self, servo_id)
+    }
+
+    fn set_mode(&self, servo_id: ServoId, mode: Mode) -> Result<(), ControllerError> {
+        ServoController::set_mode(self, servo_id, mode)
+    }
+
+    fn set_angle_limit(&self, servo_id: ServoId, min: Position, max: Position) -> Result<(), ControllerError> {
+        ServoController::set_angle_limit(self, servo_id, min, max)
+    }
+
+    fn get_position(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Position, ControllerError> {
+        ServoController::get_position(self, servo_id, timeout)
+    }
+
+    fn get_mode(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Mode, ControllerError> {
+        ServoController::get_mode(self, servo_id, timeout)
+    }
+
+    fn get_temperature(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Celsius, ControllerError> {
+        ServoController::get_temperature(self, servo_id, timeout)
+    }
+
+    fn get_voltage(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<Millivolts, ControllerError> {
+        ServoController::get_voltage(self, servo_id, timeout)
+    }
+
+    fn get_status(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<ServoStatus, ControllerError> {
+        ServoController::get_status(self, servo_id, timeout)
+    }
+
+    fn read_angle_limit(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<AngleLimit, ControllerError> {
+        ServoController::read_angle_limit(self, servo_id, timeout)
+    }
+
+    fn ping(&self, servo_id: ServoId, timeout: Option<Duration>) -> Result<bool, ControllerError> {
+        ServoController::ping(self, servo_id, timeout)
+    }
+}
@@ -0,0 +1,308 @@
+//! Several serial buses driven as one robot.
+
+use std::{collections::{BTreeMap, HashMap}, time::Duration};
+
+use crate::builder::GroupFailurePolicy;
+use crate::controller::{check_id, ServoController};
+use crate::error::{CapturePoseError, ControllerError, MoveManyError};
+use crate::pose::Pose;
+use crate::protocol::{SERVO_ID_ALL, SERVO_MOVE_START};
+
+#[cfg(feature = "config")]
+use crate::joint::Joint;
+#[cfg(feature = "config")]
+use crate::joint_map::JointMap;
+
+/// Named [`ServoController`]s, each owning a set of servo ids.
+///
+/// Ids are unique across the manager, so every per-servo operation is routed by id alone
+/// and poses and move lists look the same as on a single bus. Errors from a bus come
+/// wrapped in `ControllerError::Bus`, naming it.
+#[derive(Clone, Default)]
+pub struct BusManager {
+    buses: BTreeMap<String, ServoController>,
+    routes: HashMap<u8, String>,
+}
+
+impl BusManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `controller` as bus `name`, serving `ids`. Fails without adding anything if
+    /// the name is taken or an id is already served by another bus. Each bus must be its
+    /// own port, not a clone of a controller already added.
+    pub fn add_bus(&mut self, name: impl Into<String>, controller: ServoController, ids: &[u8]) -> Result<(), ControllerError> {
+        let name = name.into();
+        if self.buses.contains_key(&name) {
+            return Err(ControllerError::InvalidParameter(format!("bus {} added twice", name)));
+        }
+        for &servo_id in ids {
+            check_id(servo_id.into())?;
+            if let Some(bus) = self.routes.get(&servo_id) {
+                return Err(ControllerError::InvalidParameter(format!("servo {} is already on bus {}", servo_id, bus)));
+            }
+        }
+
+        for &servo_id in ids {
+            self.routes.insert(servo_id, name.clone());
+        }
+        self.buses.insert(name, controller);
+        Ok(())
+    }
+
+    pub fn bus(&self, name: &str) -> Option<&ServoController> {
+        self.buses.get(name)
+    }
+
+    /// Name of the bus serving `servo_id`.
+    pub fn bus_of(&self, servo_id: u8) -> Option<&str> {
+        self.routes.get(&servo_id).map(String::as_str)
+    }
+
+    /// The controller to talk to `servo_id` through.
+    pub fn controller_for(&self, servo_id: u8) -> Option<&ServoController> {
+        self.bus_of(servo_id).and_then(|bus| self.buses.get(bus))
+    }
+
+    /// Every servo id served, in order.
+    pub fn servo_ids(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.routes.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Joint `name` of `map`, on whichever bus serves its id.
+    #[cfg(feature = "config")]
+    pub fn joint<'a>(&'a self, map: &JointMap, name: &str) -> Option<Joint<'a>> {
+        let config = map.joints.get(name)?;
+        map.joint(name, self.controller_for(config.id)?)
+    }
+
+    /// [`ServoController::capture_pose`] across buses; pass [`servo_ids`](Self::servo_ids)
+    /// for every servo. An id no bus serves is reported as failed.
+    pub fn capture_pose(&self, ids: &[u8], timeout: Option<Duration>) -> Result<Pose, CapturePoseError> {
+        let mut pose = Pose::default();
+        let mut failed = Vec::new();
+        for &servo_id in ids {
+            let read = match self.route(servo_id) {
+                Ok((bus, controller)) => controller.get_position(servo_id, timeout).map_err(|e| on_bus(bus, e)),
+                Err(e) => Err(e),
+            };
+            match read {
+                Ok(position) => { pose.positions.insert(servo_id, position); }
+                Err(e) => {
+                    pose.missing.push(servo_id);
+                    failed.push((servo_id, e));
+                }
+            }
+        }
+
+        if failed.is_empty() { Ok(pose) } else { Err(CapturePoseError { pose, failed }) }
+    }
+
+    /// [`ServoController::apply_pose`] across buses, through [`move_group`](Self::move_group).
+    pub fn apply_pose(&self, pose: &Pose, time: u16, allow_missing: bool) -> Result<(), ControllerError> {
+        if !pose.is_complete() && !allow_missing {
+            return Err(ControllerError::InvalidParameter(format!("pose is missing servos {:?}", pose.missing)));
+        }
+
+        let moves: Vec<(u8, u16, u16)> = pose.positions.iter()
+            .map(|(&servo_id, position)| (servo_id, position.clamped().as_ticks() as u16, time))
+            .collect();
+        Ok(self.move_group(&moves)?)
+    }
+
+    /// [`ServoController::move_group`] across buses: stages every move on its bus, then
+    /// sends the broadcast starts.
+    ///
+    /// The starts go out back to back with every bus already locked, so they leave within
+    /// a syscall or two of each other. A failed start is reported under the broadcast id,
+    /// wrapped with its bus.
+    pub fn move_group(&self, moves: &[(u8, u16, u16)]) -> Result<(), MoveManyError> {
+        self.move_group_with(moves, GroupFailurePolicy::Abort)
+    }
+
+    /// [`move_group`](Self::move_group) with a choice of what to do when staging fails.
+    /// With `StartStaged` only buses with a staged move get a start.
+    pub fn move_group_with(&self, moves: &[(u8, u16, u16)], policy: GroupFailurePolicy) -> Result<(), MoveManyError> {
+        let mut error = MoveManyError { succeeded: Vec::new(), failed: Vec::new() };
+        let mut per_bus: BTreeMap<&str, Vec<(u8, u16, u16)>> = BTreeMap::new();
+        for &(servo_id, position, time) in moves {
+            match self.route(servo_id) {
+                Ok((bus, _)) => per_bus.entry(bus).or_default().push((servo_id, position, time)),
+                Err(e) => error.failed.push((servo_id, e)),
+            }
+        }
+
+        let mut staged_on = Vec::new();
+        for (&bus, moves) in &per_bus {
+            let staged = self.buses[bus].stage(moves, false, None);
+            if !staged.succeeded.is_empty() {
+                staged_on.push(bus);
+            }
+            error.succeeded.extend(staged.succeeded);
+            error.failed.extend(staged.failed.into_iter().map(|(servo_id, e)| (servo_id, on_bus(bus, e))));
+        }
+
+        let start = error.failed.is_empty() || (policy == GroupFailurePolicy::StartStaged && !error.succeeded.is_empty());
+        if start {
+            // Lock everything first, in name order, so the starts aren't held up by other callers
+            let mut locks: Vec<_> = staged_on.iter().map(|&bus| (bus, self.buses[bus].lock_bus())).collect();
+            for (bus, lock) in &mut locks {
+                if let Err(e) = lock.send(SERVO_ID_ALL, SERVO_MOVE_START, &[]) {
+                    error.failed.push((SERVO_ID_ALL, on_bus(bus, e)));
+                }
+            }
+        }
+
+        if error.failed.is_empty() { Ok(()) } else { Err(error) }
+    }
+
+    fn route(&self, servo_id: u8) -> Result<(&str, &ServoController), ControllerError> {
+        self.routes.get(&servo_id)
+            .map(|bus| (bus.as_str(), &self.buses[bus]))
+            .ok_or_else(|| ControllerError::InvalidParameter(format!("servo {} isn't on any bus", servo_id)))
+    }
+}
+
+fn on_bus(bus: &str, source: ControllerError) -> ControllerError {
+    ControllerError::Bus { bus: bus.to_string(), source: Box::new(source) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_MOVE_TIME_WAIT_WRITE, SERVO_POS_READ};
+    use crate::tap::Direction;
+    use crate::units::Position;
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    // (bus, servo, command) of every packet sent on any bus, in order
+    type Sent = Arc<Mutex<Vec<(&'static str, u8, u8)>>>;
+
+    // Three buses of six servos, like a hexapod
+    fn hexapod() -> (BusManager, BTreeMap<&'static str, MockTransport>, Sent) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = BusManager::new();
+        let mut mocks = BTreeMap::new();
+        for (name, first) in [("left", 1), ("middle", 7), ("right", 13)] {
+            let mock = MockTransport::new();
+            let controller = mock.controller(TIMEOUT);
+            let record = Arc::clone(&sent);
+            controller.set_packet_tap(Box::new(move |direction, bytes| {
+                if direction == Direction::Tx {
+                    let mut sent = record.lock().unwrap();
+                    let mut rest = bytes;
+                    while rest.len() > 4 {
+                        sent.push((name, rest[2], rest[4]));
+                        rest = &rest[usize::from(rest[3]) + 3..];
+                    }
+                }
+            }));
+            let ids: Vec<u8> = (first..first + 6).collect();
+            manager.add_bus(name, controller, &ids).unwrap();
+            mocks.insert(name, mock);
+        }
+        (manager, mocks, sent)
+    }
+
+    #[test]
+    fn servos_are_routed_to_the_bus_serving_them() {
+        let (manager, mocks, _) = hexapod();
+        assert_eq!(manager.servo_ids(), (1..=18).collect::<Vec<u8>>());
+        assert_eq!([manager.bus_of(1), manager.bus_of(12), manager.bus_of(18), manager.bus_of(19)], [Some("left"), Some("middle"), Some("right"), None]);
+
+        manager.controller_for(8).unwrap().move_servo(8, 500u16, 0).unwrap();
+        assert_eq!(mocks["middle"].written().len(), 1);
+        assert!(mocks["left"].written().is_empty() && mocks["right"].written().is_empty());
+    }
+
+    #[test]
+    fn a_bus_name_or_servo_is_only_added_once() {
+        let (mut manager, _, _) = hexapod();
+        let controller = MockTransport::new().controller(TIMEOUT);
+        assert!(matches!(manager.add_bus("left", controller.clone(), &[20]), Err(ControllerError::InvalidParameter(_))));
+        assert!(matches!(manager.add_bus("tail", controller.clone(), &[20, 6]), Err(ControllerError::InvalidParameter(_))));
+        // Nothing of a refused bus is kept
+        assert_eq!(manager.bus_of(20), None);
+        assert!(manager.bus("tail").is_none());
+        manager.add_bus("tail", controller, &[20]).unwrap();
+        assert_eq!(manager.bus_of(20), Some("tail"));
+    }
+
+    #[test]
+    fn capture_pose_reads_each_servo_on_its_bus_and_names_the_failing_one() {
+        let (manager, mocks, _) = hexapod();
+        for id in [2, 9] {
+            let bus = &mocks[manager.bus_of(id).unwrap()];
+            bus.reply_to(id, SERVO_POS_READ, MockReply::frame(id, SERVO_POS_READ, &(u16::from(id) * 10).to_le_bytes()));
+        }
+        let error = manager.capture_pose(&[2, 9, 14, 30], None).unwrap_err();
+        assert_eq!(error.pose.positions.get(&2), Some(&Position::from(20u16)));
+        assert_eq!(error.pose.positions.get(&9), Some(&Position::from(90u16)));
+        assert_eq!(error.pose.missing, [14, 30]);
+        match &error.failed[..] {
+            [(14, ControllerError::Bus { bus, source }), (30, ControllerError::InvalidParameter(_))] => {
+                assert_eq!(bus, "right");
+                assert!(matches!(**source, ControllerError::Timeout));
+            }
+            other => panic!("unexpected failures {:?}", other),
+        }
+    }
+
+    #[test]
+    fn move_group_stages_on_every_bus_before_any_start() {
+        let (manager, mocks, sent) = hexapod();
+        let moves: Vec<(u8, u16, u16)> = (1..=18).map(|id| (id, 500, 300)).collect();
+        manager.move_group(&moves).unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 18 + 3);
+        assert!(sent[..18].iter().all(|&(_, _, command)| command == SERVO_MOVE_TIME_WAIT_WRITE));
+        // The three starts close the sequence, back to back and in bus order
+        assert_eq!(sent[18..], [("left", SERVO_ID_ALL, SERVO_MOVE_START), ("middle", SERVO_ID_ALL, SERVO_MOVE_START), ("right", SERVO_ID_ALL, SERVO_MOVE_START)]);
+        for (name, mock) in &mocks {
+            let staged: Vec<u8> = mock.written().iter().filter(|frame| frame.command == SERVO_MOVE_TIME_WAIT_WRITE).map(|frame| frame.servo_id).collect();
+            assert_eq!(staged.iter().map(|&id| manager.bus_of(id)).collect::<Vec<_>>(), [Some(*name); 6]);
+        }
+    }
+
+    #[test]
+    fn move_group_starts_nothing_when_a_bus_fails_to_stage() {
+        let (manager, mocks, _) = hexapod();
+        mocks["middle"].fail_next_write(std::io::ErrorKind::BrokenPipe);
+        let error = manager.move_group(&[(1, 500, 0), (8, 500, 0), (14, 500, 0)]).unwrap_err();
+        assert_eq!(error.succeeded, [1, 14]);
+        assert!(matches!(&error.failed[..], [(8, ControllerError::Bus { bus, .. })] if bus.as_str() == "middle"));
+        assert!(mocks.values().all(|mock| mock.written().iter().all(|frame| frame.command != SERVO_MOVE_START)));
+
+        // Unless asked to start the rest anyway, on the buses that staged something
+        mocks["middle"].fail_next_write(std::io::ErrorKind::BrokenPipe);
+        manager.move_group_with(&[(1, 500, 0), (8, 500, 0), (14, 500, 0)], GroupFailurePolicy::StartStaged).unwrap_err();
+        let started: Vec<&str> = mocks.iter()
+            .filter(|(_, mock)| mock.written().iter().any(|frame| frame.command == SERVO_MOVE_START))
+            .map(|(&name, _)| name)
+            .collect();
+        assert_eq!(started, ["left", "right"]);
+    }
+
+    #[test]
+    fn apply_pose_moves_servos_on_every_bus() {
+        let (manager, mocks, _) = hexapod();
+        let mut pose = Pose::default();
+        pose.positions.insert(3, Position::from(300u16));
+        pose.positions.insert(16, Position::from(600u16));
+        manager.apply_pose(&pose, 250, false).unwrap();
+        assert_eq!(mocks["left"].written()[0].params(), [0x2C, 0x01, 0xFA, 0x00]);
+        assert_eq!(mocks["right"].written()[0].params(), [0x58, 0x02, 0xFA, 0x00]);
+        assert!(mocks["middle"].written().is_empty());
+
+        pose.missing.push(10);
+        assert!(matches!(manager.apply_pose(&pose, 250, false), Err(ControllerError::InvalidParameter(_))));
+    }
+}
@@ -0,0 +1,50 @@
+//! [`Transport`] adapter for `embedded-io` serial peripherals.
+
+use embedded_io::{Read, ReadExactError, Write};
+
+use crate::transport::Transport;
+
+/// Wraps a UART implementing the `embedded-io` traits.
+///
+/// The LX-16A bus is half duplex; the UART (or the external buffer around it)
+/// is expected to hand back only what the servos send.
+pub struct EmbeddedTransport<S> {
+    serial: S,
+}
+
+impl<S> EmbeddedTransport<S> {
+    pub fn new(serial: S) -> Self {
+        EmbeddedTransport { serial }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.serial
+    }
+}
+
+impl<S: Read + Write> Transport for EmbeddedTransport<S> {
+    type Error = ReadExactError<S::Error>;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.serial.write_all(bytes).map_err(ReadExactError::Other)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.serial.read_exact(buf)
+    }
+
+    fn read_some(&mut self, buf: &mut [u8], min: usize) -> Result<usize, Self::Error> {
+        let mut read = 0;
+        while read < min {
+            match self.serial.read(&mut buf[read..]).map_err(ReadExactError::Other)? {
+                0 => return Err(ReadExactError::UnexpectedEof),
+                n => read += n,
+            }
+        }
+        Ok(read)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.serial.flush().map_err(ReadExactError::Other)
+    }
+}
@@ -0,0 +1,243 @@
+use std::{fmt, io};
+
+use crate::id::ServoId;
+use crate::pose::Pose;
+use crate::protocol::{self, format_packet, CommandName, FrameError};
+use crate::units::Position;
+
+#[derive(Debug)]
+pub enum ControllerError {
+    SerialPortError(serialport::Error),
+    IoError(io::Error),
+    Timeout,
+    /// A read was addressed to the broadcast id, which no servo answers.
+    BroadcastQuery,
+    Protocol(FrameError),
+    /// An argument outside what the servo accepts.
+    InvalidParameter(String),
+    /// Opening the serial port failed.
+    Open { port: String, source: serialport::Error },
+    /// I/O failure on an open, named port.
+    Port { port: String, source: io::Error },
+    /// Writing `packet` to the bus failed.
+    Write { packet: Vec<u8>, source: io::Error },
+    /// A received frame failed validation; `frame` holds the bytes as read.
+    InvalidFrame { error: FrameError, frame: Vec<u8> },
+    /// A valid frame that doesn't carry what the request expects.
+    UnexpectedResponse { frame: Vec<u8> },
+    /// Reading back a written setting returned something else; params of both commands.
+    VerificationFailed { command: u8, wrote: Vec<u8>, read_back: Vec<u8> },
+    /// A servo didn't reach its target, or didn't settle, in time; `final_position` is the last reading.
+    MoveTimedOut { final_position: Position },
+    /// A move was refused because the servo was last seen unloaded; see `TorqueCheck`.
+    TorqueDisabled { servo_id: ServoId },
+    /// A query kept failing; `source` is the error of the last of `attempts` tries.
+    RetriesExhausted { attempts: u32, source: Box<ControllerError> },
+    /// A multi-servo operation failed at `servo_id`.
+    Servo { servo_id: ServoId, source: Box<ControllerError> },
+    /// An operation failed on the `BusManager` bus named `bus`.
+    Bus { bus: String, source: Box<ControllerError> },
+    /// The `BusWorker` has shut down, or died running a request.
+    WorkerStopped,
+    /// The controller was shut down with `ServoController::shutdown`.
+    ShutDown,
+}
+
+impl ControllerError {
+    /// Serial port the error happened on, when known.
+    pub fn port(&self) -> Option<&str> {
+        match self {
+            ControllerError::Open { port, .. } | ControllerError::Port { port, .. } => Some(port),
+            _ => None,
+        }
+    }
+
+    /// Raw bytes involved: the outgoing packet for write failures, the received frame otherwise.
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            ControllerError::Write { packet, .. } => Some(packet),
+            ControllerError::InvalidFrame { frame, .. } | ControllerError::UnexpectedResponse { frame } => Some(frame),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ControllerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControllerError::SerialPortError(err) => write!(f, "serial port error: {}", err),
+            ControllerError::IoError(err) => write!(f, "I/O error: {}", err),
+            ControllerError::Timeout => write!(f, "timed out waiting for a response"),
+            ControllerError::BroadcastQuery => write!(f, "servos don't answer broadcast queries; address a single servo"),
+            ControllerError::Protocol(err) => write!(f, "protocol error: {}", err),
+            ControllerError::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+            ControllerError::Open { port, source } => write!(f, "failed to open {}: {}", port, source),
+            ControllerError::Port { port, source } => write!(f, "I/O error on {}: {}", port, source),
+            ControllerError::Write { packet, source } => write!(f, "failed to write [{}]: {}", format_packet(packet), source),
+            ControllerError::InvalidFrame { error, frame } => write!(f, "invalid frame [{}]: {}", format_packet(frame), error),
+            ControllerError::UnexpectedResponse { frame } => write!(f, "unexpected response [{}]", format_packet(frame)),
+            ControllerError::VerificationFailed { command, wrote, read_back } =>
+                write!(f, "{} wrote [{}] but read back [{}]", CommandName(*command), format_packet(wrote), format_packet(read_back)),
+            ControllerError::MoveTimedOut { final_position } =>
+                write!(f, "move timed out at position {}", final_position.as_ticks()),
+            ControllerError::TorqueDisabled { servo_id } => write!(f, "servo {} is unloaded, enable torque before moving it", servo_id),
+            ControllerError::RetriesExhausted { attempts, source } => write!(f, "failed after {} attempts: {}", attempts, source),
+            ControllerError::Servo { servo_id, source } => write!(f, "servo {}: {}", servo_id, source),
+            ControllerError::Bus { bus, source } => write!(f, "bus {}: {}", bus, source),
+            ControllerError::WorkerStopped => write!(f, "the bus worker has stopped"),
+            ControllerError::ShutDown => write!(f, "the controller has been shut down"),
+        }
+    }
+}
+
+impl std::error::Error for ControllerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ControllerError::SerialPortError(err) | ControllerError::Open { source: err, .. } => Some(err),
+            ControllerError::IoError(err) | ControllerError::Port { source: err, .. } | ControllerError::Write { source: err, .. } => Some(err),
+            ControllerError::Servo { source, .. } | ControllerError::RetriesExhausted { source, .. } | ControllerError::Bus { source, .. } =>
+                Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a [`move_many`](crate::ServoController::move_many) or
+/// [`move_group`](crate::ServoController::move_group) where some moves failed.
+#[derive(Debug)]
+pub struct MoveManyError {
+    /// Ids whose move was sent (staged, for a group), in order.
+    pub succeeded: Vec<u8>,
+    /// Ids whose move wasn't sent, with the reason.
+    pub failed: Vec<(u8, ControllerError)>,
+}
+
+impl fmt::Display for MoveManyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} moves failed", self.failed.len(), self.failed.len() + self.succeeded.len())?;
+        for (servo_id, err) in &self.failed {
+            write!(f, "; servo {}: {}", servo_id, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MoveManyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.failed.first().map(|(_, err)| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Keeps the first failure, for callers that only propagate a single error.
+impl From<MoveManyError> for ControllerError {
+    fn from(err: MoveManyError) -> ControllerError {
+        match err.failed.into_iter().next() {
+            Some((servo_id, source)) => ControllerError::Servo { servo_id: servo_id.into(), source: Box::new(source) },
+            None => ControllerError::InvalidParameter("move_many failed without a failing servo".to_string()),
+        }
+    }
+}
+
+/// A [`capture_pose`](crate::ServoController::capture_pose) where some servos couldn't be read.
+#[derive(Debug)]
+pub struct CapturePoseError {
+    /// The servos that were read, with the others listed in `missing`.
+    pub pose: Pose,
+    pub failed: Vec<(u8, ControllerError)>,
+}
+
+impl fmt::Display for CapturePoseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to read {} of {} servos", self.failed.len(), self.failed.len() + self.pose.positions.len())?;
+        for (servo_id, err) in &self.failed {
+            write!(f, "; servo {}: {}", servo_id, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CapturePoseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.failed.first().map(|(_, err)| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<serialport::Error> for ControllerError {
+    fn from(err: serialport::Error) -> ControllerError {
+        ControllerError::SerialPortError(err)
+    }
+}
+
+impl From<io::Error> for ControllerError {
+    fn from(err: io::Error) -> ControllerError {
+        match err.kind() {
+            io::ErrorKind::TimedOut => ControllerError::Timeout,
+            _ => ControllerError::IoError(err),
+        }
+    }
+}
+
+impl From<FrameError> for ControllerError {
+    fn from(err: FrameError) -> ControllerError {
+        ControllerError::Protocol(err)
+    }
+}
+
+impl From<protocol::Error<io::Error>> for ControllerError {
+    fn from(err: protocol::Error<io::Error>) -> ControllerError {
+        match err {
+            protocol::Error::Transport(err) => err.into(),
+            protocol::Error::Frame { error, raw } => ControllerError::InvalidFrame { error, frame: raw.as_bytes().to_vec() },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use super::ControllerError;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{FrameError, SERVO_POS_READ};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn a_corrupted_reply_keeps_its_bytes() {
+        let mock = MockTransport::new();
+        let corrupted = [0x55, 0x55, 0x01, 0x05, SERVO_POS_READ, 0xF4, 0x01, 0xE9];
+        mock.reply(MockReply::bytes(&corrupted));
+        let err = mock.controller(TIMEOUT).get_position(1, None).unwrap_err();
+
+        assert!(matches!(err, ControllerError::InvalidFrame { error: FrameError::ChecksumMismatch { expected: 0xE8, received: 0xE9 }, .. }));
+        assert_eq!(err.bytes(), Some(&corrupted[..]));
+        assert_eq!(err.to_string(), "invalid frame [55 55 01 05 1C F4 01 E9]: checksum E9, expected E8");
+    }
+
+    #[test]
+    fn an_unexpected_reply_keeps_its_bytes() {
+        let mock = MockTransport::new();
+        mock.reply(MockReply::frame(1, SERVO_POS_READ, &[0x01]));
+        let err = mock.controller(TIMEOUT).get_position(1, None).unwrap_err();
+        assert_eq!(err.bytes(), Some(&[0x55, 0x55, 0x01, 0x04, SERVO_POS_READ, 0x01, 0xDD][..]));
+        assert!(err.to_string().contains("[55 55 01 04 1C 01 DD]"), "{}", err);
+    }
+
+    #[test]
+    fn a_failed_write_keeps_the_packet() {
+        let mock = MockTransport::new();
+        mock.fail_next_write(io::ErrorKind::BrokenPipe);
+        let err = mock.controller(TIMEOUT).get_position(1, None).unwrap_err();
+        assert_eq!(err.bytes(), Some(&[0x55, 0x55, 0x01, 0x03, SERVO_POS_READ, 0xDF][..]));
+        assert!(err.to_string().starts_with("failed to write [55 55 01 03 1C DF]: "), "{}", err);
+    }
+
+    #[test]
+    fn port_errors_name_the_port() {
+        let err = ControllerError::Port { port: "/dev/ttyUSB0".to_string(), source: io::ErrorKind::BrokenPipe.into() };
+        assert_eq!(err.port(), Some("/dev/ttyUSB0"));
+        assert!(err.to_string().starts_with("I/O error on /dev/ttyUSB0: "), "{}", err);
+        assert_eq!(ControllerError::Timeout.port(), None);
+        assert_eq!(ControllerError::Timeout.bytes(), None);
+    }
+}
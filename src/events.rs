@@ -0,0 +1,297 @@
+//! Change notifications for a set of servos, fed by a background polling thread.
+
+use std::{collections::HashMap, sync::mpsc, thread, time::{Duration, Instant}};
+
+use log::debug;
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::types::ServoFault;
+use crate::units::Position;
+
+/// Something a [`Subscription`] noticed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServoEvent {
+    /// The position moved at least `min_delta` ticks from the last one reported.
+    PositionChanged { servo_id: ServoId, position: Position, at: Instant },
+    /// Fault flags that weren't set at the previous check; `faults` holds all current ones.
+    FaultRaised { servo_id: ServoId, faults: ServoFault },
+    /// The servo stopped answering. Sent once per outage, not for every failed read.
+    Timeout { servo_id: ServoId },
+}
+
+/// How a [`Subscription`] polls and when it reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubscribeOptions {
+    /// Position reads per second, per servo.
+    pub rate_hz: f32,
+    /// Smallest position change reported, in ticks.
+    pub min_delta: u16,
+    /// Also read the fault flags every this many rounds; 0 never does.
+    pub faults_every: u32,
+    /// Events held for a slow consumer before new ones are dropped.
+    pub capacity: usize,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        SubscribeOptions { rate_hz: 20.0, min_delta: 2, faults_every: 10, capacity: 64 }
+    }
+}
+
+/// Thread started by [`ServoController::subscribe`], delivering [`ServoEvent`]s.
+///
+/// Events queue in a bounded channel. While it is full new events are dropped, not
+/// blocked on: the poller keeps its pace and a dropped position change is reported on the
+/// next round that still differs from the last delivered position, so nothing is lost
+/// for good but the steps in between. Stops when [`stop`](Self::stop) is called or the
+/// subscription is dropped.
+pub struct Subscription {
+    events: mpsc::Receiver<ServoEvent>,
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Subscription {
+    pub(crate) fn spawn(controller: ServoController, ids: Vec<u8>, options: SubscribeOptions) -> Result<Subscription, ControllerError> {
+        if !options.rate_hz.is_finite() || options.rate_hz <= 0.0 {
+            return Err(ControllerError::InvalidParameter(format!("poll rate {} Hz must be positive", options.rate_hz)));
+        }
+        if options.capacity == 0 {
+            return Err(ControllerError::InvalidParameter("event capacity must be above 0".to_string()));
+        }
+
+        let period = Duration::from_secs_f32(1.0 / options.rate_hz);
+        let (sender, events) = mpsc::sync_channel(options.capacity);
+        let (stop, stopped) = mpsc::channel();
+        let stopper = stop.clone();
+        let running = controller.register_thread(move || { let _ = stopper.send(()); });
+        let thread = thread::Builder::new()
+            .name("lx16a-events".to_string())
+            .spawn(move || {
+                let _running = running;
+                let mut watched: HashMap<u8, Watched> = ids.iter()
+                    .map(|&servo_id| (servo_id, Watched { position: None, faults: ServoFault::empty(), timed_out: false }))
+                    .collect();
+                let mut next = Instant::now();
+                for round in 0u64.. {
+                    let faults = options.faults_every != 0 && round % options.faults_every as u64 == 0;
+                    for &servo_id in &ids {
+                        let Some(state) = watched.get_mut(&servo_id) else { continue };
+                        if state.check(&controller, servo_id.into(), faults, options.min_delta, &sender).is_none() {
+                            return;
+                        }
+                    }
+
+                    next += period;
+                    let now = Instant::now();
+                    if next < now {
+                        next = now;
+                    }
+                    if !matches!(stopped.recv_timeout(next - now), Err(mpsc::RecvTimeoutError::Timeout)) {
+                        return;
+                    }
+                }
+            })
+            .map_err(ControllerError::IoError)?;
+
+        Ok(Subscription { events, stop: Some(stop), thread: Some(thread) })
+    }
+
+    /// The events, in the order they were noticed.
+    pub fn events(&self) -> &mpsc::Receiver<ServoEvent> {
+        &self.events
+    }
+
+    /// Stops the thread, waiting for a round in progress to finish. Undelivered events are lost.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        // A clone of the sender is kept for ServoController::shutdown, so send rather than drop
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+// What was last delivered for one servo
+struct Watched {
+    position: Option<Position>,
+    faults: ServoFault,
+    // A Timeout was delivered and the servo hasn't answered since
+    timed_out: bool,
+}
+
+impl Watched {
+    // None once the receiver is gone
+    fn check(&mut self, controller: &ServoController, servo_id: ServoId, read_faults: bool, min_delta: u16, sender: &mpsc::SyncSender<ServoEvent>) -> Option<()> {
+        match controller.get_position(servo_id, None) {
+            Ok(position) => {
+                self.timed_out = false;
+                let moved = self.position.is_none_or(|last| last.as_ticks().abs_diff(position.as_ticks()) >= min_delta);
+                if moved && deliver(sender, ServoEvent::PositionChanged { servo_id, position, at: Instant::now() })? {
+                    self.position = Some(position);
+                }
+            }
+            Err(e) => {
+                debug!("Polling servo {} failed: {}", servo_id, e);
+                if !self.timed_out {
+                    // Retried next round if the channel was full
+                    self.timed_out = deliver(sender, ServoEvent::Timeout { servo_id })?;
+                }
+                return Some(());
+            }
+        }
+
+        if read_faults {
+            match controller.read_faults(servo_id, None) {
+                Ok(faults) => {
+                    let raised = faults.difference(self.faults);
+                    if raised.is_empty() || deliver(sender, ServoEvent::FaultRaised { servo_id, faults })? {
+                        self.faults = faults;
+                    }
+                }
+                Err(e) => debug!("Reading faults of servo {} failed: {}", servo_id, e),
+            }
+        }
+        Some(())
+    }
+}
+
+// Some(true) when sent, Some(false) when the channel is full, None once it is closed
+fn deliver(sender: &mpsc::SyncSender<ServoEvent>, event: ServoEvent) -> Option<bool> {
+    match sender.try_send(event) {
+        Ok(()) => Some(true),
+        Err(mpsc::TrySendError::Full(event)) => {
+            debug!("Dropping {:?}, the subscriber is behind", event);
+            Some(false)
+        }
+        Err(mpsc::TrySendError::Disconnected(_)) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_LED_ERROR_READ, SERVO_POS_READ};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn options(faults_every: u32, capacity: usize) -> SubscribeOptions {
+        SubscribeOptions { rate_hz: 200.0, min_delta: 5, faults_every, capacity }
+    }
+
+    // One reading per round, then silence
+    fn script_positions(mock: &MockTransport, servo_id: u8, readings: &[i16]) {
+        for &ticks in readings {
+            mock.reply_to(servo_id, SERVO_POS_READ, MockReply::frame(servo_id, SERVO_POS_READ, &ticks.to_le_bytes()));
+        }
+    }
+
+    fn next(subscription: &Subscription) -> ServoEvent {
+        subscription.events().recv_timeout(Duration::from_secs(2)).expect("an event")
+    }
+
+    fn moved_to(event: ServoEvent) -> i16 {
+        match event {
+            ServoEvent::PositionChanged { servo_id, position, .. } => {
+                assert_eq!(servo_id, ServoId::from(1));
+                position.as_ticks()
+            }
+            other => panic!("expected a position change, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn motion_is_reported_past_the_minimum_delta_then_one_timeout() {
+        let mock = MockTransport::new();
+        script_positions(&mock, 1, &[500, 502, 504, 505, 520, 523, 540, 540]);
+        let subscription = mock.controller(TIMEOUT).subscribe(&[1], options(0, 16)).unwrap();
+
+        // 502 and 504 are within 5 of 500; 505 is not
+        let positions: Vec<i16> = (0..4).map(|_| moved_to(next(&subscription))).collect();
+        assert_eq!(positions, [500, 505, 520, 540]);
+        assert_eq!(next(&subscription), ServoEvent::Timeout { servo_id: 1.into() });
+        // The outage is reported once, however many reads fail
+        thread::sleep(Duration::from_millis(50));
+        assert!(subscription.events().try_recv().is_err());
+    }
+
+    #[test]
+    fn a_servo_answering_again_after_a_timeout_can_time_out_again() {
+        let mock = MockTransport::new();
+        let subscription = mock.controller(TIMEOUT).subscribe(&[1], options(0, 16)).unwrap();
+        assert_eq!(next(&subscription), ServoEvent::Timeout { servo_id: 1.into() });
+        script_positions(&mock, 1, &[300]);
+        assert_eq!(moved_to(next(&subscription)), 300);
+        assert_eq!(next(&subscription), ServoEvent::Timeout { servo_id: 1.into() });
+    }
+
+    #[test]
+    fn only_newly_raised_faults_are_reported() {
+        let mock = MockTransport::new();
+        script_positions(&mock, 1, &[500; 6]);
+        let over_temperature = ServoFault::OVER_TEMPERATURE.bits();
+        let locked_rotor = ServoFault::LOCKED_ROTOR.bits();
+        for flags in [0, over_temperature, over_temperature, over_temperature | locked_rotor, 0, locked_rotor] {
+            mock.reply_to(1, SERVO_LED_ERROR_READ, MockReply::frame(1, SERVO_LED_ERROR_READ, &[flags]));
+        }
+        let subscription = mock.controller(TIMEOUT).subscribe(&[1], options(1, 16)).unwrap();
+
+        assert_eq!(moved_to(next(&subscription)), 500);
+        let raised = |faults| ServoEvent::FaultRaised { servo_id: 1.into(), faults };
+        assert_eq!(next(&subscription), raised(ServoFault::OVER_TEMPERATURE));
+        assert_eq!(next(&subscription), raised(ServoFault::OVER_TEMPERATURE | ServoFault::LOCKED_ROTOR));
+        // Cleared, then raised again
+        assert_eq!(next(&subscription), raised(ServoFault::LOCKED_ROTOR));
+        assert_eq!(next(&subscription), ServoEvent::Timeout { servo_id: 1.into() });
+    }
+
+    #[test]
+    fn a_slow_consumer_loses_the_steps_in_between_but_not_the_latest_position() {
+        let mock = MockTransport::new();
+        let mut readings = vec![100, 200, 300, 400];
+        readings.extend([450; 40]);
+        script_positions(&mock, 1, &readings);
+        let subscription = mock.controller(TIMEOUT).subscribe(&[1], options(0, 2)).unwrap();
+
+        // Fall behind while the channel is full
+        thread::sleep(Duration::from_millis(60));
+        let positions: Vec<i16> = (0..3).map(|_| moved_to(next(&subscription))).collect();
+        assert_eq!(positions, [100, 200, 450]);
+    }
+
+    #[test]
+    fn stopping_ends_the_thread_and_the_events() {
+        let mock = MockTransport::new();
+        script_positions(&mock, 1, &[500; 100]);
+        let subscription = mock.controller(TIMEOUT).subscribe(&[1], options(0, 16)).unwrap();
+        assert_eq!(moved_to(next(&subscription)), 500);
+        subscription.stop();
+
+        let written = mock.written().len();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(mock.written().len(), written);
+    }
+
+    #[test]
+    fn bad_options_are_refused() {
+        let controller = MockTransport::new().controller(TIMEOUT);
+        for options in [options(0, 0), SubscribeOptions { rate_hz: 0.0, ..SubscribeOptions::default() }] {
+            assert!(matches!(controller.subscribe(&[1], options), Err(ControllerError::InvalidParameter(_))));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use core::fmt;
+
+use crate::protocol::SERVO_ID_ALL;
+
+/// Address of a servo on the bus: 0..=253, or [`ServoId::BROADCAST`].
+///
+/// `From<u8>` keeps integer literals working at call sites; ids that slip through
+/// it unchecked (255) are rejected when the command is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ServoId(u8);
+
+/// Returned by [`ServoId::new`] for ids outside 0..=253.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidServoId(pub u8);
+
+impl ServoId {
+    /// Every servo on the bus. Servos never answer broadcast packets, so the
+    /// controller only accepts it for writes and rejects queries up front.
+    pub const BROADCAST: ServoId = ServoId(SERVO_ID_ALL);
+    pub const MAX: u8 = 253;
+
+    pub const fn new(id: u8) -> Result<ServoId, InvalidServoId> {
+        if id <= Self::MAX { Ok(ServoId(id)) } else { Err(InvalidServoId(id)) }
+    }
+
+    pub const fn is_broadcast(self) -> bool {
+        self.0 == SERVO_ID_ALL
+    }
+
+    /// A single servo or the broadcast address.
+    pub const fn is_valid(self) -> bool {
+        self.0 <= SERVO_ID_ALL
+    }
+}
+
+impl From<u8> for ServoId {
+    fn from(id: u8) -> ServoId {
+        ServoId(id)
+    }
+}
+
+impl From<ServoId> for u8 {
+    fn from(id: ServoId) -> u8 {
+        id.0
+    }
+}
+
+impl fmt::Display for ServoId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_broadcast() { write!(f, "broadcast") } else { write!(f, "{}", self.0) }
+    }
+}
+
+impl fmt::Display for InvalidServoId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "servo id {} outside 0..={}", self.0, ServoId::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_accepts_0_to_253() {
+        assert_eq!(ServoId::new(0).map(u8::from), Ok(0));
+        assert_eq!(ServoId::new(253).map(u8::from), Ok(253));
+        assert_eq!(ServoId::new(254), Err(InvalidServoId(254)));
+        assert_eq!(ServoId::new(255), Err(InvalidServoId(255)));
+    }
+
+    #[test]
+    fn broadcast_is_254() {
+        assert_eq!(u8::from(ServoId::BROADCAST), 254);
+        assert!(ServoId::from(254).is_broadcast());
+        assert!(!ServoId::from(253).is_broadcast());
+        assert!(ServoId::BROADCAST.is_valid());
+        assert!(!ServoId::from(255).is_valid());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(ServoId::from(7).to_string(), "7");
+        assert_eq!(ServoId::BROADCAST.to_string(), "broadcast");
+        assert_eq!(InvalidServoId(255).to_string(), "servo id 255 outside 0..=253");
+    }
+}
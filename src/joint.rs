@@ -0,0 +1,233 @@
+//! Servos configured as the joints of a mechanism.
+
+use std::time::Duration;
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::units::{self, Position};
+
+/// One servo seen through its place in a mechanism: joint angles in degrees, with
+/// their own zero, direction and travel range.
+///
+/// The servo angle for a joint angle is `offset_deg ± angle`, minus when `invert` is
+/// set. Joint angles are clamped to `min_deg..=max_deg` before conversion. The tick
+/// methods ([`move_to`](Self::move_to), [`position`](Self::position)) apply the same
+/// transform with the offset rounded to whole ticks, so converting there and back is exact.
+#[derive(Clone, Copy)]
+pub struct Joint<'a> {
+    controller: &'a ServoController,
+    pub servo_id: ServoId,
+    pub min_deg: f32,
+    pub max_deg: f32,
+    pub invert: bool,
+    /// Servo angle at joint angle 0.
+    pub offset_deg: f32,
+    /// Joint angle [`ServoController::home_all`] moves to, clamped to the limits.
+    pub home_deg: f32,
+}
+
+impl<'a> Joint<'a> {
+    /// A joint spanning the servo's whole 0..=240° range, with joint and servo angles equal.
+    pub fn new(controller: &'a ServoController, servo_id: impl Into<ServoId>) -> Self {
+        Joint {
+            controller,
+            servo_id: servo_id.into(),
+            min_deg: 0.0,
+            max_deg: units::MAX_DEGREES,
+            invert: false,
+            offset_deg: 0.0,
+            home_deg: 0.0,
+        }
+    }
+
+    pub fn with_limits(mut self, min_deg: f32, max_deg: f32) -> Self {
+        self.min_deg = min_deg;
+        self.max_deg = max_deg;
+        self
+    }
+
+    pub fn inverted(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    pub fn with_offset(mut self, offset_deg: f32) -> Self {
+        self.offset_deg = offset_deg;
+        self
+    }
+
+    pub fn with_home(mut self, home_deg: f32) -> Self {
+        self.home_deg = home_deg;
+        self
+    }
+
+    /// Sets the offset in ticks of 0.24°.
+    pub fn with_offset_ticks(mut self, offset_ticks: i16) -> Self {
+        self.offset_deg = offset_ticks as f32 * units::DEGREES_PER_TICK;
+        self
+    }
+
+    /// `degrees` clamped to the joint's limits.
+    pub fn clamp(&self, degrees: f32) -> f32 {
+        degrees.max(self.min_deg).min(self.max_deg)
+    }
+
+    pub fn to_servo_degrees(&self, degrees: f32) -> f32 {
+        let degrees = if self.invert { -degrees } else { degrees };
+        self.offset_deg + degrees
+    }
+
+    pub fn from_servo_degrees(&self, servo_degrees: f32) -> f32 {
+        let degrees = servo_degrees - self.offset_deg;
+        if self.invert { -degrees } else { degrees }
+    }
+
+    /// Servo position of the home angle, clamped to the limits.
+    pub fn home_position(&self) -> Position {
+        Position::from_degrees(self.to_servo_degrees(self.clamp(self.home_deg)))
+    }
+
+    /// Servo position for joint position `position`, both in ticks.
+    pub fn to_servo_position(&self, position: Position) -> Position {
+        let offset = Position::from_degrees(self.offset_deg);
+        if self.invert { offset - position.as_ticks() } else { offset + position.as_ticks() }
+    }
+
+    /// Joint position for servo position `position`; the inverse of [`to_servo_position`](Self::to_servo_position).
+    pub fn from_servo_position(&self, position: Position) -> Position {
+        let ticks = position - Position::from_degrees(self.offset_deg);
+        if self.invert { Position::from_ticks(0) - ticks } else { Position::from_ticks(ticks) }
+    }
+
+    /// Moves to joint position `position` in ticks, clamped to the limits, and returns
+    /// the position actually commanded.
+    pub fn move_to(&self, position: impl Into<Position>, time: u16) -> Result<Position, ControllerError> {
+        let min = Position::from_ticks((self.min_deg * units::TICKS_PER_DEGREE).ceil() as i16);
+        let max = Position::from_ticks((self.max_deg * units::TICKS_PER_DEGREE).floor() as i16);
+        let position = position.into().max(min).min(max);
+        self.controller.move_servo(self.servo_id, self.to_servo_position(position), time)?;
+        Ok(position)
+    }
+
+    /// Current joint position in ticks, unclamped like [`angle`](Self::angle).
+    pub fn position(&self, timeout: Option<Duration>) -> Result<Position, ControllerError> {
+        Ok(self.from_servo_position(self.controller.get_position(self.servo_id, timeout)?))
+    }
+
+    /// Moves `delta_deg` from the current joint angle, clamped to the limits, and returns
+    /// the angle commanded.
+    pub fn move_relative(&self, delta_deg: f32, time: u16, timeout: Option<Duration>) -> Result<f32, ControllerError> {
+        if !delta_deg.is_finite() {
+            return Err(ControllerError::InvalidParameter(format!("relative move of {} degrees", delta_deg)));
+        }
+
+        self.go_to(self.angle(timeout)? + delta_deg, time)
+    }
+
+    /// Moves to joint angle `degrees` (clamped to the limits) over `time` ms and returns
+    /// the angle actually commanded.
+    ///
+    /// Fails if the clamped angle still maps outside the servo's 0..=240°, which means
+    /// the offset and limits don't fit the servo.
+    pub fn go_to(&self, degrees: f32, time: u16) -> Result<f32, ControllerError> {
+        if degrees.is_nan() {
+            return Err(ControllerError::InvalidParameter("joint angle is NaN".to_string()));
+        }
+
+        let degrees = self.clamp(degrees);
+        self.controller.move_to_degrees(self.servo_id, self.to_servo_degrees(degrees), time)?;
+        Ok(degrees)
+    }
+
+    /// Current joint angle. Not clamped, so it shows when the joint was pushed past its limits.
+    pub fn angle(&self, timeout: Option<Duration>) -> Result<f32, ControllerError> {
+        Ok(self.from_servo_degrees(self.controller.get_position_degrees(self.servo_id, timeout)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_MOVE_TIME_WRITE, SERVO_POS_READ};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn joints(controller: &ServoController) -> [Joint<'_>; 4] {
+        [
+            Joint::new(controller, 1),
+            Joint::new(controller, 1).inverted(true).with_offset_ticks(1000),
+            Joint::new(controller, 1).with_offset_ticks(37),
+            Joint::new(controller, 1).inverted(true).with_offset_ticks(812),
+        ]
+    }
+
+    fn sent_positions(mock: &MockTransport) -> Vec<i16> {
+        mock.written().iter()
+            .filter(|frame| frame.command == SERVO_MOVE_TIME_WRITE)
+            .map(|frame| i16::from_le_bytes([frame.params()[0], frame.params()[1]]))
+            .collect()
+    }
+
+    #[test]
+    fn ticks_survive_joint_to_servo_and_back() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        for joint in joints(&controller) {
+            for ticks in -1000..=1000 {
+                let position = Position::from_ticks(ticks);
+                assert_eq!(joint.from_servo_position(joint.to_servo_position(position)), position, "{} ticks", ticks);
+            }
+        }
+    }
+
+    #[test]
+    fn degrees_survive_joint_to_servo_and_back() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        for joint in joints(&controller) {
+            for tenth in -2400..=2400 {
+                let degrees = tenth as f32 / 10.0;
+                let back = joint.from_servo_degrees(joint.to_servo_degrees(degrees));
+                assert!((back - degrees).abs() < 1e-3, "{}° came back as {}°", degrees, back);
+            }
+        }
+    }
+
+    #[test]
+    fn an_inverted_offset_joint_mirrors_moves_and_readings() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        let joint = Joint::new(&controller, 1).inverted(true).with_offset_ticks(812);
+        assert_eq!(joint.move_to(100u16, 0).unwrap(), Position::from(100u16));
+        assert_eq!(sent_positions(&mock), [712]);
+
+        mock.reply(MockReply::frame(1, SERVO_POS_READ, &712i16.to_le_bytes()));
+        assert_eq!(joint.position(None).unwrap(), Position::from(100u16));
+        mock.reply(MockReply::frame(1, SERVO_POS_READ, &812i16.to_le_bytes()));
+        assert_eq!(joint.angle(None).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn limits_apply_in_joint_angles_before_the_transform() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        let joint = Joint::new(&controller, 1).inverted(true).with_offset_ticks(1000).with_limits(0.0, 120.0);
+        // Joint 120° is tick 500, reached from the top of the servo's range
+        assert_eq!(joint.move_to(900u16, 0).unwrap(), Position::from(500u16));
+        assert_eq!(joint.go_to(-30.0, 0).unwrap(), 0.0);
+        assert_eq!(sent_positions(&mock), [500, 1000]);
+    }
+
+    #[test]
+    fn relative_moves_are_in_the_joint_frame() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        let joint = Joint::new(&controller, 1).inverted(true).with_offset_ticks(1000);
+        // Servo tick 750 is joint 60°, and joint 72° is servo 168°, tick 700
+        mock.reply(MockReply::frame(1, SERVO_POS_READ, &750i16.to_le_bytes()));
+        assert_eq!(joint.move_relative(12.0, 0, None).unwrap(), 72.0);
+        assert_eq!(sent_positions(&mock), [700]);
+    }
+}
@@ -0,0 +1,222 @@
+//! Named joints loaded from a TOML file.
+//!
+//! ```toml
+//! [joints.elbow]
+//! id = 4
+//! min_deg = 10
+//! max_deg = 200
+//! inverted = true
+//! offset_ticks = 12
+//! home_deg = 90
+//! ```
+
+use std::{cmp::Ordering, collections::{BTreeMap, HashMap}, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::controller::ServoController;
+use crate::id::ServoId;
+use crate::joint::Joint;
+use crate::units;
+
+/// One `[joints.<name>]` table. Everything but `id` is optional; see [`Joint`] for
+/// what the fields mean.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JointConfig {
+    pub id: u8,
+    #[serde(default)]
+    pub min_deg: f32,
+    #[serde(default = "max_degrees")]
+    pub max_deg: f32,
+    #[serde(default)]
+    pub inverted: bool,
+    #[serde(default)]
+    pub offset_deg: f32,
+    /// Added to `offset_deg`, for zeros measured in ticks.
+    #[serde(default)]
+    pub offset_ticks: i16,
+    /// Joint angle to home to; see [`Joint::home_deg`].
+    #[serde(default)]
+    pub home_deg: f32,
+}
+
+impl JointConfig {
+    /// Servo angle at joint angle 0: `offset_deg` plus `offset_ticks`.
+    pub fn offset_degrees(&self) -> f32 {
+        self.offset_deg + self.offset_ticks as f32 * units::DEGREES_PER_TICK
+    }
+}
+
+fn max_degrees() -> f32 {
+    units::MAX_DEGREES
+}
+
+/// Joint configurations by name.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct JointMap {
+    #[serde(default)]
+    pub joints: BTreeMap<String, JointConfig>,
+}
+
+/// Why a joint map couldn't be loaded.
+#[derive(Debug)]
+pub enum JointMapError {
+    Parse(toml::de::Error),
+    /// Every problem found, one message each.
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for JointMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JointMapError::Parse(err) => write!(f, "invalid joint map: {}", err),
+            JointMapError::Invalid(problems) => write!(f, "invalid joint map: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for JointMapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JointMapError::Parse(err) => Some(err),
+            JointMapError::Invalid(_) => None,
+        }
+    }
+}
+
+impl JointMap {
+    /// Parses and [validates](Self::validate) a TOML document.
+    pub fn from_toml(text: &str) -> Result<JointMap, JointMapError> {
+        let map: JointMap = toml::from_str(text).map_err(JointMapError::Parse)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Checks for ids outside 0..=253, ids used twice, and limits that are empty or map
+    /// outside the servo's 0..=240°. Reports every problem, not just the first.
+    pub fn validate(&self) -> Result<(), JointMapError> {
+        let mut problems = Vec::new();
+        let mut by_id: HashMap<u8, &str> = HashMap::new();
+
+        for (name, config) in &self.joints {
+            if ServoId::new(config.id).is_err() {
+                problems.push(format!("{}: id {} outside 0..={}", name, config.id, ServoId::MAX));
+            }
+            if let Some(other) = by_id.insert(config.id, name) {
+                problems.push(format!("{}: id {} already used by {}", name, config.id, other));
+            }
+
+            if config.min_deg.partial_cmp(&config.max_deg) != Some(Ordering::Less) {
+                problems.push(format!("{}: min_deg {} not below max_deg {}", name, config.min_deg, config.max_deg));
+                continue;
+            }
+            let offset = config.offset_degrees();
+            let ends = if config.inverted {
+                [offset - config.max_deg, offset - config.min_deg]
+            } else {
+                [offset + config.min_deg, offset + config.max_deg]
+            };
+            if ends.iter().any(|end| !(0.0..=units::MAX_DEGREES).contains(end)) {
+                problems.push(format!("{}: limits map to servo angles {}°..{}°, outside 0..={}°", name, ends[0], ends[1], units::MAX_DEGREES));
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(JointMapError::Invalid(problems)) }
+    }
+
+    /// The joint called `name`, driven through `controller`.
+    pub fn joint<'a>(&self, name: &str, controller: &'a ServoController) -> Option<Joint<'a>> {
+        self.joints.get(name).map(|config| {
+            Joint::new(controller, config.id)
+                .with_limits(config.min_deg, config.max_deg)
+                .inverted(config.inverted)
+                .with_offset(config.offset_degrees())
+                .with_home(config.home_deg)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::mock::MockTransport;
+
+    const SAMPLE: &str = r#"
+        [joints.elbow]
+        id = 4
+        min_deg = 10
+        max_deg = 200
+        inverted = true
+        offset_deg = 220
+
+        [joints.wrist]
+        id = 5
+    "#;
+
+    #[test]
+    fn an_inverted_joint_from_toml_encodes_the_mirrored_move() {
+        let map = JointMap::from_toml(SAMPLE).unwrap();
+        let mock = MockTransport::new();
+        let controller = mock.controller(Duration::from_millis(20));
+        let elbow = map.joint("elbow", &controller).unwrap();
+        assert_eq!(elbow.go_to(50.0, 1000).unwrap(), 50.0);
+        // Servo angle 220° - 50° = 170°, tick 708
+        assert_eq!(mock.written_bytes(), [0x55, 0x55, 0x04, 0x07, 0x01, 0xC4, 0x02, 0xE8, 0x03, 0x42]);
+
+        // Clamped to the joint's limits before mirroring
+        mock.clear_written();
+        assert_eq!(elbow.go_to(250.0, 0).unwrap(), 200.0);
+        assert_eq!(mock.written()[0].params(), [0x53, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let map = JointMap::from_toml(SAMPLE).unwrap();
+        assert_eq!(map.joints["wrist"], JointConfig {
+            id: 5, min_deg: 0.0, max_deg: 240.0, inverted: false, offset_deg: 0.0, offset_ticks: 0, home_deg: 0.0,
+        });
+        assert!(map.joint("knee", &MockTransport::new().controller(Duration::from_millis(20))).is_none());
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let toml = r#"
+            [joints.a]
+            id = 4
+            [joints.b]
+            id = 4
+            [joints.c]
+            id = 254
+            [joints.d]
+            id = 6
+            min_deg = 100
+            max_deg = 50
+            [joints.e]
+            id = 7
+            max_deg = 200
+            offset_deg = 60
+        "#;
+        let Err(JointMapError::Invalid(problems)) = JointMap::from_toml(toml) else {
+            panic!("expected the map to be refused");
+        };
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].starts_with("b: id 4 already used by a"), "{}", problems[0]);
+        assert!(problems[1].starts_with("c: id 254 outside"), "{}", problems[1]);
+        assert!(problems[2].starts_with("d: min_deg 100 not below max_deg 50"), "{}", problems[2]);
+        assert!(problems[3].starts_with("e: limits map to servo angles 60°..260°"), "{}", problems[3]);
+    }
+
+    #[test]
+    fn malformed_toml_is_a_parse_error() {
+        assert!(matches!(JointMap::from_toml("[joints.elbow]\nmin_deg = 10\n"), Err(JointMapError::Parse(_))));
+        assert!(matches!(JointMap::from_toml("[joints.elbow\n"), Err(JointMapError::Parse(_))));
+    }
+
+    #[test]
+    fn a_map_survives_a_toml_round_trip() {
+        let map = JointMap::from_toml(SAMPLE).unwrap();
+        assert_eq!(JointMap::from_toml(&toml::to_string(&map).unwrap()).unwrap(), map);
+    }
+}
@@ -0,0 +1,95 @@
+//! Driver for LewanSoul / Hiwonder LX-16A serial bus servos.
+//!
+//! The [`protocol`] and [`transport`] modules are `no_std` and allocation
+//! free. [`ServoController`] needs the default `std` feature and talks to the
+//! bus through `serialport`.
+//!
+//! Optional features: `embedded-io` adds a [`Transport`] for `embedded-io` UARTs,
+//! `serde` derives `Serialize`/`Deserialize` on the plain data types, and `config`
+//! loads named joints from TOML (`joint_map`), and `async` adds an
+//! [`AsyncServoController`] that runs on any executor, with `tokio-serial` or
+//! `async-serial` to open ports on tokio or smol/async-std. `test-util` adds a
+//! scripted [`MockTransport`](mock::MockTransport) for testing without servos.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod id;
+pub mod protocol;
+pub mod transport;
+pub mod types;
+pub mod units;
+pub mod velocity;
+
+#[cfg(feature = "embedded-io")]
+pub mod embedded;
+
+#[cfg(feature = "async")]
+pub mod async_controller;
+#[cfg(feature = "std")]
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod bus;
+#[cfg(feature = "std")]
+pub mod bus_manager;
+#[cfg(feature = "std")]
+mod controller;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod joint;
+#[cfg(feature = "config")]
+pub mod joint_map;
+#[cfg(all(feature = "std", any(test, feature = "test-util")))]
+pub mod mock;
+#[cfg(feature = "std")]
+pub mod move_command;
+#[cfg(feature = "std")]
+pub mod poller;
+#[cfg(feature = "std")]
+pub mod pose;
+#[cfg(feature = "std")]
+pub mod tap;
+#[cfg(feature = "std")]
+pub mod watchdog;
+#[cfg(feature = "std")]
+pub mod worker;
+
+#[cfg(feature = "async")]
+pub use async_controller::{AsyncServoController, AsyncTransport, BoxedAsyncTransport};
+#[cfg(feature = "std")]
+pub use builder::{AveragingOptions, DropAction, GroupFailurePolicy, QueryOptions, ReconnectPolicy, RetryPolicy, ServoControllerBuilder, SoftLimit, SoftLimitPolicy, TorqueCheck};
+#[cfg(feature = "std")]
+pub use bus::ServoBus;
+#[cfg(feature = "std")]
+pub use bus_manager::BusManager;
+#[cfg(feature = "std")]
+pub use controller::{BoxedTransport, DeviceInfo, PositionStream, ServoController, TorqueGuard, DEFAULT_MAX_WRITE_LEN, PING_TIMEOUT};
+#[cfg(feature = "std")]
+pub use error::{CapturePoseError, ControllerError, MoveManyError};
+#[cfg(feature = "std")]
+pub use events::{ServoEvent, SubscribeOptions, Subscription};
+#[cfg(feature = "std")]
+pub use joint::Joint;
+#[cfg(feature = "config")]
+pub use joint_map::{JointConfig, JointMap, JointMapError};
+#[cfg(all(feature = "std", any(test, feature = "test-util")))]
+pub use mock::{MockReply, MockTransport};
+#[cfg(feature = "std")]
+pub use move_command::{MoveCommand, Tolerance};
+#[cfg(feature = "std")]
+pub use poller::{PollOptions, PolledStatus, Poller};
+#[cfg(feature = "std")]
+pub use pose::Pose;
+#[cfg(feature = "std")]
+pub use tap::{Direction, LatencyHook, PacketTap};
+#[cfg(feature = "std")]
+pub use watchdog::Watchdog;
+#[cfg(feature = "std")]
+pub use worker::{BusWorker, WorkerHandle};
+pub use id::ServoId;
+pub use transport::Transport;
+pub use types::{AngleLimit, AveragedPosition, Mode, MotionState, ServoConfig, ServoFault, ServoStatus};
+pub use units::{Celsius, Millivolts, Position};
+pub use velocity::VelocityEstimator;
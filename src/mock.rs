@@ -0,0 +1,282 @@
+//! A scripted stand-in for the serial port, for exercising the controller without servos.
+
+use std::{collections::{HashMap, VecDeque}, io, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use crate::controller::{BoxedTransport, ServoController};
+use crate::protocol::{reply_len, Frame, FRAME_HEADER, MAX_FRAME_LEN};
+use crate::transport::Transport;
+
+/// What a [`MockTransport`] sends back for a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockReply {
+    input: MockInput,
+    delay: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MockInput {
+    Bytes(Vec<u8>),
+    Error(io::ErrorKind),
+}
+
+impl MockReply {
+    /// A well-formed reply frame.
+    ///
+    /// Panics if `params` don't fit a frame.
+    pub fn frame(servo_id: u8, command: u8, params: &[u8]) -> MockReply {
+        let frame = Frame::new(servo_id, command, params).expect("scripted reply fits a frame");
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        MockReply::bytes(frame.encode(&mut buf))
+    }
+
+    /// Raw bytes, e.g. half a frame for a short read or a corrupted checksum.
+    pub fn bytes(bytes: &[u8]) -> MockReply {
+        MockReply { input: MockInput::Bytes(bytes.to_vec()), delay: Duration::ZERO }
+    }
+
+    /// The read fails with `kind` instead of returning bytes.
+    pub fn error(kind: io::ErrorKind) -> MockReply {
+        MockReply { input: MockInput::Error(kind), delay: Duration::ZERO }
+    }
+
+    /// Available `delay` after the query was written, rather than straight away.
+    pub fn after(mut self, delay: Duration) -> MockReply {
+        self.delay = delay;
+        self
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    written: Vec<Frame>,
+    written_bytes: Vec<u8>,
+    // The bytes of each write call
+    writes: Vec<Vec<u8>>,
+    // Written bytes not yet making up a whole frame
+    unparsed: Vec<u8>,
+    keyed: HashMap<(u8, u8), VecDeque<MockReply>>,
+    queued: VecDeque<MockReply>,
+    // Replies in the order they become readable
+    input: VecDeque<(Instant, MockInput)>,
+    write_errors: VecDeque<io::ErrorKind>,
+    flush_errors: VecDeque<io::ErrorKind>,
+    // Bytes written since the last flush
+    unflushed: usize,
+    timeout: Option<Duration>,
+}
+
+impl MockState {
+    fn parse_written(&mut self) {
+        loop {
+            let Some(start) = self.unparsed.windows(2).position(|pair| pair == [FRAME_HEADER, FRAME_HEADER]) else {
+                let keep = usize::from(self.unparsed.last() == Some(&FRAME_HEADER));
+                self.unparsed.drain(..self.unparsed.len() - keep);
+                return;
+            };
+            self.unparsed.drain(..start);
+            let Some(&length) = self.unparsed.get(3) else { return };
+            let end = length as usize + 3;
+            if self.unparsed.len() < end {
+                return;
+            }
+            match Frame::decode(&self.unparsed[..end]) {
+                Ok(frame) => {
+                    self.unparsed.drain(..end);
+                    self.answer(frame);
+                    self.written.push(frame);
+                }
+                // Not a frame after all; look for the next header
+                Err(_) => { self.unparsed.drain(..1); }
+            }
+        }
+    }
+
+    fn answer(&mut self, frame: Frame) {
+        let reply = match self.keyed.get_mut(&(frame.servo_id, frame.command)).and_then(VecDeque::pop_front) {
+            Some(reply) => reply,
+            None if reply_len(frame.command).is_some() => match self.queued.pop_front() {
+                Some(reply) => reply,
+                None => return,
+            },
+            None => return,
+        };
+        let at = Instant::now() + reply.delay;
+        let index = self.input.iter().position(|(ready, _)| *ready > at).unwrap_or(self.input.len());
+        self.input.insert(index, (at, reply.input));
+    }
+
+    fn available(&self) -> usize {
+        let now = Instant::now();
+        self.input.iter()
+            .take_while(|(ready, _)| *ready <= now)
+            .map(|(_, input)| match input {
+                MockInput::Bytes(bytes) => bytes.len(),
+                MockInput::Error(_) => 0,
+            })
+            .sum()
+    }
+}
+
+/// A [`Transport`] that records what is written and answers queries from a script.
+///
+/// Written bytes are split into frames as they arrive. Each query frame is answered by
+/// the next reply scripted for its servo and command with [`reply_to`](Self::reply_to),
+/// or failing that the next one queued with [`reply`](Self::reply); commands that get no
+/// reply never take a queued one. A read beyond the scripted replies times out at once
+/// rather than after the timeout, so tests of missing servos stay fast; a delayed reply
+/// is waited for in real time, as long as the timeout allows.
+///
+/// Clones share the script and the record, so keep one to inspect after handing the
+/// other to a controller.
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A controller over a clone of this transport, with a `timeout` default.
+    pub fn controller(&self, timeout: Duration) -> ServoController {
+        ServoController::with_transport(self.boxed(), timeout).expect("the mock accepts any timeout")
+    }
+
+    /// A clone boxed for [`ServoController::with_transport`](crate::ServoController::with_transport).
+    pub fn boxed(&self) -> BoxedTransport {
+        Box::new(self.clone())
+    }
+
+    /// Queues `reply` for the next query not answered by a keyed reply.
+    pub fn reply(&self, reply: MockReply) {
+        self.state.lock().unwrap().queued.push_back(reply);
+    }
+
+    /// Queues `reply` for the next frame written to `servo_id` with `command`.
+    pub fn reply_to(&self, servo_id: u8, command: u8, reply: MockReply) {
+        self.state.lock().unwrap().keyed.entry((servo_id, command)).or_default().push_back(reply);
+    }
+
+    /// Makes the next write fail with `kind`; nothing of it is recorded.
+    pub fn fail_next_write(&self, kind: io::ErrorKind) {
+        self.state.lock().unwrap().write_errors.push_back(kind);
+    }
+
+    /// Makes the next flush fail with `kind`.
+    pub fn fail_next_flush(&self, kind: io::ErrorKind) {
+        self.state.lock().unwrap().flush_errors.push_back(kind);
+    }
+
+    /// Bytes written since the last flush.
+    pub fn unflushed(&self) -> usize {
+        self.state.lock().unwrap().unflushed
+    }
+
+    /// Every frame written so far, in order.
+    pub fn written(&self) -> Vec<Frame> {
+        self.state.lock().unwrap().written.clone()
+    }
+
+    /// Every byte written so far, frames or not.
+    pub fn written_bytes(&self) -> Vec<u8> {
+        self.state.lock().unwrap().written_bytes.clone()
+    }
+
+    /// The bytes of each successful write call, in order, for checking how writes were batched.
+    pub fn writes(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().writes.clone()
+    }
+
+    pub fn clear_written(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.written.clear();
+        state.written_bytes.clear();
+        state.writes.clear();
+    }
+
+    /// Scripted replies no query has taken yet.
+    pub fn unused_replies(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.queued.len() + state.keyed.values().map(VecDeque::len).sum::<usize>()
+    }
+}
+
+impl Transport for MockTransport {
+    type Error = io::Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(kind) = state.write_errors.pop_front() {
+            return Err(kind.into());
+        }
+        state.written_bytes.extend_from_slice(bytes);
+        state.writes.push(bytes.to_vec());
+        state.unflushed += bytes.len();
+        state.unparsed.extend_from_slice(bytes);
+        state.parse_written();
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let deadline = self.state.lock().unwrap().timeout.map(|timeout| Instant::now() + timeout);
+        let mut filled = 0;
+        while filled < buf.len() {
+            let mut state = self.state.lock().unwrap();
+            let Some((ready, _)) = state.input.front() else {
+                return Err(io::ErrorKind::TimedOut.into());
+            };
+            let now = Instant::now();
+            if *ready > now {
+                let ready = *ready;
+                drop(state);
+                if let Some(deadline) = deadline.filter(|&deadline| deadline < ready) {
+                    thread::sleep(deadline.saturating_duration_since(now));
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                thread::sleep(ready - now);
+                continue;
+            }
+
+            match &mut state.input.front_mut().unwrap().1 {
+                MockInput::Error(kind) => {
+                    let kind = *kind;
+                    state.input.pop_front();
+                    return Err(kind.into());
+                }
+                MockInput::Bytes(bytes) => {
+                    let n = bytes.len().min(buf.len() - filled);
+                    buf[filled..filled + n].copy_from_slice(&bytes[..n]);
+                    bytes.drain(..n);
+                    filled += n;
+                    if bytes.is_empty() {
+                        state.input.pop_front();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(kind) = state.flush_errors.pop_front() {
+            return Err(kind.into());
+        }
+        state.unflushed = 0;
+        Ok(())
+    }
+
+    fn bytes_to_read(&mut self) -> Result<Option<usize>, Self::Error> {
+        Ok(Some(self.state.lock().unwrap().available()))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.state.lock().unwrap().timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        self.state.lock().unwrap().timeout = Some(timeout);
+        Ok(())
+    }
+}
@@ -0,0 +1,283 @@
+//! Fluent construction of a single move, for when the positional methods get unwieldy.
+
+use std::time::{Duration, Instant};
+
+use crate::controller::{move_time_ms, ServoController};
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::units::{self, Position};
+
+/// How close a servo has to get to its target for a waited move to count as done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tolerance(u16);
+
+impl Tolerance {
+    pub const fn ticks(ticks: u16) -> Tolerance {
+        Tolerance(ticks)
+    }
+
+    /// Rounds to the nearest tick.
+    pub fn degrees(degrees: f32) -> Tolerance {
+        Tolerance((degrees.abs() * units::TICKS_PER_DEGREE).round() as u16)
+    }
+
+    pub const fn as_ticks(self) -> u16 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Ticks(Position),
+    Degrees(f32),
+}
+
+/// A move being put together; nothing is sent until [`send`](Self::send).
+///
+/// Created by [`ServoController::move_cmd`]. Each option lowers onto the matching
+/// controller method, so a `MoveCommand` behaves exactly like the call it replaces, e.g.
+/// `controller.move_cmd(3).to_degrees(90.0).over(Duration::from_millis(800)).wait(Tolerance::ticks(5)).send()?`
+/// is a [`move_servo`](ServoController::move_servo) followed by polling until settled.
+#[must_use = "a move command does nothing until sent"]
+pub struct MoveCommand<'a> {
+    controller: &'a ServoController,
+    servo_id: ServoId,
+    target: Option<Target>,
+    over: Option<Duration>,
+    speed: Option<f32>,
+    wait: Option<Tolerance>,
+    wait_timeout: Duration,
+    prepared: bool,
+    timeout: Option<Duration>,
+}
+
+impl<'a> MoveCommand<'a> {
+    pub(crate) fn new(controller: &'a ServoController, servo_id: ServoId) -> Self {
+        MoveCommand {
+            controller,
+            servo_id,
+            target: None,
+            over: None,
+            speed: None,
+            wait: None,
+            wait_timeout: Duration::from_millis(500),
+            prepared: false,
+            timeout: None,
+        }
+    }
+
+    /// Target in ticks. Plain `u16`s are raw ticks.
+    pub fn to(mut self, position: impl Into<Position>) -> Self {
+        self.target = Some(Target::Ticks(position.into()));
+        self
+    }
+
+    pub fn to_degrees(mut self, degrees: f32) -> Self {
+        self.target = Some(Target::Degrees(degrees));
+        self
+    }
+
+    /// Move time; a zero-time move when neither this nor [`speed`](Self::speed) is set.
+    pub fn over(mut self, duration: Duration) -> Self {
+        self.over = Some(duration);
+        self
+    }
+
+    /// Derives the move time from the distance to travel, in ticks per second; see
+    /// [`ServoController::move_with_speed`]. Can't be combined with [`over`](Self::over).
+    pub fn speed(mut self, ticks_per_sec: f32) -> Self {
+        self.speed = Some(ticks_per_sec);
+        self
+    }
+
+    /// Blocks in `send` until the servo is within `tolerance` of the target.
+    pub fn wait(mut self, tolerance: Tolerance) -> Self {
+        self.wait = Some(tolerance);
+        self
+    }
+
+    /// How long past the move time a waited move may take before failing with
+    /// `MoveTimedOut`; 500 ms unless set.
+    pub fn wait_timeout(mut self, timeout: Duration) -> Self {
+        self.wait_timeout = timeout;
+        self
+    }
+
+    /// Stages the move for [`ServoController::move_start`] instead of starting it.
+    pub fn prepared(mut self) -> Self {
+        self.prepared = true;
+        self
+    }
+
+    /// Timeout for the queries `speed` and `wait` make.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the move. Returns the settled position when [`wait`](Self::wait) was
+    /// requested, `None` otherwise.
+    ///
+    /// Fails with `InvalidParameter`, before anything is sent, when there is no target or
+    /// the options conflict: `speed` with `over`, or `prepared` with `speed` or `wait`.
+    pub fn send(self) -> Result<Option<Position>, ControllerError> {
+        let conflict = match (self.speed.is_some(), self.over.is_some(), self.prepared, self.wait.is_some()) {
+            (true, true, _, _) => Some("speed and over both set the move time"),
+            (true, _, true, _) => Some("a prepared move can't derive its time from the speed"),
+            (_, _, true, true) => Some("a prepared move doesn't start, so it can't be waited for"),
+            _ => None,
+        };
+        if let Some(conflict) = conflict {
+            return Err(ControllerError::InvalidParameter(conflict.to_string()));
+        }
+
+        let target = match self.target {
+            Some(Target::Ticks(position)) => position,
+            Some(Target::Degrees(degrees)) => units::degrees_to_position(degrees)
+                .map(Position::from)
+                .ok_or_else(|| ControllerError::InvalidParameter(format!("angle {}° outside 0..={}°", degrees, units::MAX_DEGREES)))?,
+            None => return Err(ControllerError::InvalidParameter("move command has no target".to_string())),
+        };
+
+        let controller = self.controller;
+        let servo_id = self.servo_id;
+        if self.wait.is_some() && servo_id.is_broadcast() {
+            return Err(ControllerError::BroadcastQuery);
+        }
+        // A waited move waits for where the soft limits let the servo go
+        let target = match (self.wait, target.to_wire()) {
+            (Some(_), Some(wire)) => Position::from(controller.apply_soft_limits(servo_id, wire)?),
+            _ => target,
+        };
+
+        let time = match self.speed {
+            Some(speed) => controller.move_with_speed(servo_id, target, speed, self.timeout)?,
+            None => {
+                let time = move_time_ms(self.over.unwrap_or(Duration::ZERO))?;
+                if self.prepared {
+                    controller.move_prepare(servo_id, target, time)?;
+                } else {
+                    controller.move_servo(servo_id, target, time)?;
+                }
+                time
+            }
+        };
+
+        let Some(tolerance) = self.wait else {
+            return Ok(None);
+        };
+        let deadline = Instant::now() + Duration::from_millis(time as u64) + self.wait_timeout;
+        controller.wait_for_target(servo_id, target, tolerance.as_ticks(), deadline).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_MOVE_TIME_WAIT_WRITE, SERVO_MOVE_TIME_WRITE, SERVO_POS_READ};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn sent(mock: &MockTransport) -> Vec<(u8, u16, u16)> {
+        mock.written().iter()
+            .filter(|frame| matches!(frame.command, SERVO_MOVE_TIME_WRITE | SERVO_MOVE_TIME_WAIT_WRITE))
+            .map(|frame| match frame.params() {
+                &[p_low, p_high, t_low, t_high] => (frame.command, u16::from_le_bytes([p_low, p_high]), u16::from_le_bytes([t_low, t_high])),
+                params => panic!("move with params {:?}", params),
+            })
+            .collect()
+    }
+
+    fn positions(mock: &MockTransport, readings: &[i16]) {
+        for ticks in readings {
+            mock.reply_to(3, SERVO_POS_READ, MockReply::frame(3, SERVO_POS_READ, &ticks.to_le_bytes()));
+        }
+    }
+
+    #[test]
+    fn a_bare_target_is_a_zero_time_move() {
+        let mock = MockTransport::new();
+        assert_eq!(mock.controller(TIMEOUT).move_cmd(3).to(400u16).send().unwrap(), None);
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WRITE, 400, 0)]);
+    }
+
+    #[test]
+    fn over_sets_the_move_time() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        assert_eq!(controller.move_cmd(3).to_degrees(90.0).over(Duration::from_millis(800)).send().unwrap(), None);
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WRITE, 375, 800)]);
+    }
+
+    #[test]
+    fn speed_derives_the_time_from_the_distance() {
+        let mock = MockTransport::new();
+        positions(&mock, &[100]);
+        assert_eq!(mock.controller(TIMEOUT).move_cmd(3).to(600u16).speed(250.0).send().unwrap(), None);
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WRITE, 600, 2000)]);
+    }
+
+    #[test]
+    fn prepared_stages_the_move() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        assert_eq!(controller.move_cmd(3).to(600u16).over(Duration::from_millis(300)).prepared().send().unwrap(), None);
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WAIT_WRITE, 600, 300)]);
+    }
+
+    #[test]
+    fn wait_returns_the_settled_position() {
+        let mock = MockTransport::new();
+        positions(&mock, &[200, 360, 373]);
+        let controller = mock.controller(TIMEOUT);
+        let settled = controller.move_cmd(3).to_degrees(90.0).over(Duration::from_millis(40)).wait(Tolerance::ticks(5)).send().unwrap();
+        assert_eq!(settled, Some(Position::from(373u16)));
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WRITE, 375, 40)]);
+        assert_eq!(mock.unused_replies(), 0);
+    }
+
+    #[test]
+    fn speed_and_wait_combine() {
+        let mock = MockTransport::new();
+        positions(&mock, &[100, 600]);
+        let settled = mock.controller(TIMEOUT).move_cmd(3).to(600u16).speed(50_000.0).wait(Tolerance::degrees(1.0)).send().unwrap();
+        assert_eq!(settled, Some(Position::from(600u16)));
+        assert_eq!(sent(&mock), [(SERVO_MOVE_TIME_WRITE, 600, 10)]);
+    }
+
+    #[test]
+    fn a_wait_that_never_settles_times_out() {
+        let mock = MockTransport::new();
+        positions(&mock, &[200; 10]);
+        let result = mock.controller(TIMEOUT).move_cmd(3).to(600u16).wait(Tolerance::ticks(5)).wait_timeout(Duration::from_millis(30)).send();
+        assert!(matches!(result, Err(ControllerError::MoveTimedOut { final_position }) if final_position == Position::from(200u16)), "{:?}", result);
+    }
+
+    #[test]
+    fn conflicting_or_incomplete_commands_send_nothing() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(TIMEOUT);
+        let commands = [
+            controller.move_cmd(3).to(600u16).speed(100.0).over(Duration::from_millis(100)),
+            controller.move_cmd(3).to(600u16).speed(100.0).prepared(),
+            controller.move_cmd(3).to(600u16).prepared().wait(Tolerance::ticks(5)),
+            controller.move_cmd(3).over(Duration::from_millis(100)),
+            controller.move_cmd(3).to_degrees(241.0),
+            controller.move_cmd(3).to(600u16).over(Duration::from_secs(31)),
+        ];
+        for command in commands {
+            assert!(matches!(command.send(), Err(ControllerError::InvalidParameter(_))));
+        }
+        let broadcast = controller.move_cmd(ServoId::BROADCAST).to(600u16).wait(Tolerance::ticks(5)).send();
+        assert!(matches!(broadcast, Err(ControllerError::BroadcastQuery)));
+        assert!(mock.written().is_empty());
+    }
+
+    #[test]
+    fn degree_tolerances_round_to_ticks() {
+        assert_eq!(Tolerance::degrees(1.0).as_ticks(), 4);
+        assert_eq!(Tolerance::degrees(-0.5).as_ticks(), 2);
+        assert_eq!(Tolerance::degrees(0.0), Tolerance::ticks(0));
+    }
+}
@@ -0,0 +1,265 @@
+//! Keeping an up to date picture of a set of servos from a background thread.
+
+use std::{collections::HashMap, sync::{mpsc, Arc, Mutex}, thread, time::{Duration, Instant}};
+
+use log::debug;
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::types::ServoStatus;
+
+/// How a [`Poller`] paces its reads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PollOptions {
+    /// Rounds per second; each round reads the position of every servo.
+    pub rate_hz: f32,
+    /// Also read temperature and voltage every this many rounds; 0 never does.
+    pub telemetry_every: u32,
+}
+
+impl PollOptions {
+    /// Positions only, `rate_hz` rounds per second.
+    pub fn new(rate_hz: f32) -> Self {
+        PollOptions { rate_hz, telemetry_every: 0 }
+    }
+}
+
+/// The latest a [`Poller`] knows about one servo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolledStatus {
+    /// Latest readings; fields keep their last good value when a read fails.
+    pub status: ServoStatus,
+    /// When the position was last read successfully.
+    pub updated: Option<Instant>,
+    /// Position reads failed in a row; 0 after a good one.
+    pub consecutive_errors: u32,
+    /// Failed reads since polling started.
+    pub total_errors: u64,
+}
+
+impl PolledStatus {
+    /// `true` when the position hasn't been read within `max_age`.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.updated.is_none_or(|updated| updated.elapsed() > max_age)
+    }
+}
+
+/// Thread started by [`ServoController::start_polling`].
+///
+/// Read failures are counted in each servo's [`PolledStatus`] and polling carries on.
+/// When a round takes longer than the period the next one starts straight away, so the
+/// effective rate drops rather than rounds piling up. Stops when [`stop`](Self::stop) is
+/// called or the poller is dropped.
+pub struct Poller {
+    latest: Arc<Mutex<HashMap<u8, PolledStatus>>>,
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Poller {
+    pub(crate) fn spawn(controller: ServoController, ids: Vec<u8>, options: PollOptions) -> Result<Poller, ControllerError> {
+        if !options.rate_hz.is_finite() || options.rate_hz <= 0.0 {
+            return Err(ControllerError::InvalidParameter(format!("poll rate {} Hz must be positive", options.rate_hz)));
+        }
+
+        let latest: Arc<Mutex<HashMap<u8, PolledStatus>>> = Arc::new(Mutex::new(ids.iter().map(|&servo_id| {
+            let status = ServoStatus { servo_id: servo_id.into(), position: None, temperature: None, voltage: None, faults: None, mode: None, torque_enabled: None };
+            (servo_id, PolledStatus { status, updated: None, consecutive_errors: 0, total_errors: 0 })
+        }).collect()));
+
+        let period = Duration::from_secs_f32(1.0 / options.rate_hz);
+        let (stop, stopped) = mpsc::channel();
+        let stopper = stop.clone();
+        let running = controller.register_thread(move || { let _ = stopper.send(()); });
+        let shared = latest.clone();
+        let thread = thread::Builder::new()
+            .name("lx16a-poller".to_string())
+            .spawn(move || {
+                let _running = running;
+                let mut next = Instant::now();
+                for round in 0u64.. {
+                    let telemetry = options.telemetry_every != 0 && round % options.telemetry_every as u64 == 0;
+                    for &servo_id in &ids {
+                        poll(&controller, servo_id.into(), telemetry, &shared);
+                    }
+
+                    next += period;
+                    let now = Instant::now();
+                    if next < now {
+                        next = now;
+                    }
+                    if !matches!(stopped.recv_timeout(next - now), Err(mpsc::RecvTimeoutError::Timeout)) {
+                        return;
+                    }
+                }
+            })
+            .map_err(ControllerError::IoError)?;
+
+        Ok(Poller { latest, stop: Some(stop), thread: Some(thread) })
+    }
+
+    /// Latest picture of `servo_id`, `None` if it isn't polled.
+    pub fn latest_status(&self, servo_id: impl Into<ServoId>) -> Option<PolledStatus> {
+        let servo_id = servo_id.into();
+        self.latest.lock().unwrap().get(&servo_id.into()).cloned()
+    }
+
+    /// Stops the thread, waiting for a round in progress to finish.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        // A clone of the sender is kept for ServoController::shutdown, so send rather than drop
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+// Reads outside the snapshot lock so readers never wait on the bus
+fn poll(controller: &ServoController, servo_id: ServoId, telemetry: bool, latest: &Mutex<HashMap<u8, PolledStatus>>) {
+    let position = controller.get_position(servo_id, None);
+    let temperature = telemetry.then(|| controller.get_temperature(servo_id, None));
+    let voltage = telemetry.then(|| controller.get_voltage(servo_id, None));
+
+    let errors = failures(servo_id, Some(&position)) + failures(servo_id, temperature.as_ref()) + failures(servo_id, voltage.as_ref());
+
+    let mut latest = latest.lock().unwrap();
+    let Some(entry) = latest.get_mut(&servo_id.into()) else { return };
+    entry.total_errors += errors;
+    match position {
+        Ok(position) => {
+            entry.status.position = Some(position);
+            entry.updated = Some(Instant::now());
+            entry.consecutive_errors = 0;
+        }
+        Err(_) => entry.consecutive_errors += 1,
+    }
+    if let Some(Ok(temperature)) = temperature {
+        entry.status.temperature = Some(temperature);
+    }
+    if let Some(Ok(voltage)) = voltage {
+        entry.status.voltage = Some(voltage);
+    }
+}
+
+fn failures<T>(servo_id: ServoId, result: Option<&Result<T, ControllerError>>) -> u64 {
+    match result {
+        Some(Err(e)) => {
+            debug!("Polling servo {} failed: {}", servo_id, e);
+            1
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_POS_READ, SERVO_TEMP_READ, SERVO_VIN_READ};
+    use crate::units::{Celsius, Millivolts, Position};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    // A servo that answers `answers` position reads, then goes quiet
+    fn servo(mock: &MockTransport, servo_id: u8, answers: usize) {
+        for _ in 0..answers {
+            mock.reply_to(servo_id, SERVO_POS_READ, MockReply::frame(servo_id, SERVO_POS_READ, &[0xF4, 0x01]));
+        }
+    }
+
+    fn reads(mock: &MockTransport, command: u8) -> usize {
+        mock.written().iter().filter(|frame| frame.command == command).count()
+    }
+
+    fn wait_until(poller: &Poller, servo_id: u8, done: impl Fn(&PolledStatus) -> bool) -> PolledStatus {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let status = poller.latest_status(servo_id).unwrap();
+            if done(&status) {
+                return status;
+            }
+            assert!(Instant::now() < deadline, "gave up waiting, last {:?}", status);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn rounds_keep_to_the_rate() {
+        let mock = MockTransport::new();
+        servo(&mock, 1, 100);
+        let poller = mock.controller(TIMEOUT).start_polling(&[1], 50.0).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        poller.stop();
+
+        // 20 ms rounds: one straight away, then about one per period
+        let rounds = reads(&mock, SERVO_POS_READ);
+        assert!((7..=12).contains(&rounds), "{} rounds in 200 ms", rounds);
+        let after_stop = mock.written().len();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(mock.written().len(), after_stop);
+    }
+
+    #[test]
+    fn a_servo_that_stops_answering_goes_stale_and_keeps_its_last_reading() {
+        let mock = MockTransport::new();
+        servo(&mock, 1, 3);
+        let poller = mock.controller(TIMEOUT).start_polling(&[1], 200.0).unwrap();
+
+        let answered = wait_until(&poller, 1, |status| status.updated.is_some());
+        assert_eq!(answered.status.position, Some(Position::from(500u16)));
+        let silent = wait_until(&poller, 1, |status| status.consecutive_errors >= 3);
+        assert_eq!(silent.status.position, Some(Position::from(500u16)));
+        assert!(silent.total_errors >= 3);
+        assert!(silent.is_stale(Duration::from_millis(5)));
+        assert!(!answered.is_stale(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn a_servo_never_heard_from_is_stale() {
+        let mock = MockTransport::new();
+        let poller = mock.controller(TIMEOUT).start_polling(&[1], 200.0).unwrap();
+        let status = wait_until(&poller, 1, |status| status.consecutive_errors >= 1);
+        assert_eq!((status.updated, status.status.position), (None, None));
+        assert!(status.is_stale(Duration::from_secs(60)));
+        assert!(poller.latest_status(2).is_none());
+    }
+
+    #[test]
+    fn telemetry_is_read_every_nth_round() {
+        let mock = MockTransport::new();
+        servo(&mock, 1, 100);
+        for _ in 0..50 {
+            mock.reply_to(1, SERVO_TEMP_READ, MockReply::frame(1, SERVO_TEMP_READ, &[41]));
+            mock.reply_to(1, SERVO_VIN_READ, MockReply::frame(1, SERVO_VIN_READ, &[0x30, 0x2A]));
+        }
+        let poller = mock.controller(TIMEOUT).start_polling_with(&[1], PollOptions { rate_hz: 200.0, telemetry_every: 3 }).unwrap();
+        let status = wait_until(&poller, 1, |_| reads(&mock, SERVO_POS_READ) >= 7);
+        poller.stop();
+
+        let positions = reads(&mock, SERVO_POS_READ);
+        assert_eq!(reads(&mock, SERVO_TEMP_READ), positions.div_ceil(3));
+        assert_eq!(reads(&mock, SERVO_VIN_READ), positions.div_ceil(3));
+        assert_eq!((status.status.temperature, status.status.voltage), (Some(Celsius(41)), Some(Millivolts(10800))));
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_are_refused() {
+        let controller = MockTransport::new().controller(TIMEOUT);
+        for rate in [0.0, -5.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(controller.start_polling(&[1], rate), Err(ControllerError::InvalidParameter(_))), "{}", rate);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Positions of a set of servos, captured together and restored together.
+
+use std::collections::BTreeMap;
+
+use crate::units::Position;
+
+/// Where each servo was at [`capture_pose`](crate::ServoController::capture_pose) time.
+///
+/// `missing` lists servos that couldn't be read; [`apply_pose`](crate::ServoController::apply_pose)
+/// refuses a pose with missing servos unless told to move the rest anyway.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pose {
+    #[cfg_attr(feature = "serde", serde(with = "servo_keys"))]
+    pub positions: BTreeMap<u8, Position>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub missing: Vec<u8>,
+}
+
+// Ids as string keys, since formats like TOML only take strings
+#[cfg(feature = "serde")]
+mod servo_keys {
+    use std::collections::BTreeMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::units::Position;
+
+    pub fn serialize<S: Serializer>(positions: &BTreeMap<u8, Position>, serializer: S) -> Result<S::Ok, S::Error> {
+        let keyed: BTreeMap<String, Position> = positions.iter().map(|(id, position)| (id.to_string(), *position)).collect();
+        keyed.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<u8, Position>, D::Error> {
+        BTreeMap::<String, Position>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(id, position)| id.parse().map(|id| (id, position)).map_err(|_| D::Error::custom(format!("servo id {:?} is not a number", id))))
+            .collect()
+    }
+}
+
+impl Pose {
+    pub fn position(&self, servo_id: u8) -> Option<Position> {
+        self.positions.get(&servo_id).copied()
+    }
+
+    /// `true` when every servo in the pose was read.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::error::ControllerError;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{SERVO_ID_ALL, SERVO_MOVE_START, SERVO_MOVE_TIME_WAIT_WRITE, SERVO_POS_READ};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn bus(positions: &[(u8, i16)]) -> MockTransport {
+        let mock = MockTransport::new();
+        for &(servo_id, ticks) in positions {
+            mock.reply_to(servo_id, SERVO_POS_READ, MockReply::frame(servo_id, SERVO_POS_READ, &ticks.to_le_bytes()));
+        }
+        mock
+    }
+
+    fn staged(mock: &MockTransport) -> Vec<(u8, u16, u16)> {
+        mock.written().iter()
+            .filter(|frame| frame.command == SERVO_MOVE_TIME_WAIT_WRITE)
+            .map(|frame| {
+                let params = frame.params();
+                (frame.servo_id, u16::from_le_bytes([params[0], params[1]]), u16::from_le_bytes([params[2], params[3]]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_captured_pose_is_restored_together() {
+        let mock = bus(&[(1, 120), (2, 870), (5, -4)]);
+        let controller = mock.controller(TIMEOUT);
+        let pose = controller.capture_pose(&[1, 2, 5], None).unwrap();
+        assert_eq!(pose.position(2), Some(Position::from(870u16)));
+        assert!(pose.is_complete());
+
+        mock.clear_written();
+        controller.apply_pose(&pose, 600, false).unwrap();
+        // A reading just below the range is clamped back onto it
+        assert_eq!(staged(&mock), [(1, 120, 600), (2, 870, 600), (5, 0, 600)]);
+        let start = *mock.written().last().unwrap();
+        assert_eq!((start.servo_id, start.command), (SERVO_ID_ALL, SERVO_MOVE_START));
+    }
+
+    #[test]
+    fn a_failed_read_is_reported_per_servo() {
+        let mock = bus(&[(1, 120), (5, 300)]);
+        let error = mock.controller(TIMEOUT).capture_pose(&[1, 2, 5], None).unwrap_err();
+        assert_eq!(error.pose.positions.keys().copied().collect::<Vec<_>>(), [1, 5]);
+        assert_eq!(error.pose.missing, [2]);
+        assert!(matches!(error.failed[..], [(2, ControllerError::Timeout)]));
+    }
+
+    #[test]
+    fn a_pose_with_missing_servos_only_moves_when_allowed() {
+        let mock = bus(&[(1, 120), (5, 300)]);
+        let controller = mock.controller(TIMEOUT);
+        let pose = controller.capture_pose(&[1, 2, 5], None).unwrap_err().pose;
+
+        mock.clear_written();
+        assert!(matches!(controller.apply_pose(&pose, 600, false), Err(ControllerError::InvalidParameter(_))));
+        assert!(mock.written().is_empty());
+
+        controller.apply_pose(&pose, 600, true).unwrap();
+        assert_eq!(staged(&mock), [(1, 120, 600), (5, 300, 600)]);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn a_pose_survives_a_file() {
+        let pose = Pose {
+            positions: [(1, Position::from(120u16)), (2, Position::from_ticks(-4))].into_iter().collect(),
+            missing: vec![7],
+        };
+        let path = std::env::temp_dir().join(format!("lx16a-pose-{}.toml", std::process::id()));
+        std::fs::write(&path, toml::to_string(&pose).unwrap()).unwrap();
+        let loaded: Pose = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, pose);
+    }
+}
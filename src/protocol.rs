@@ -0,0 +1,704 @@
+//! LX-16A bus protocol: command table, frame encoding and decoding.
+//!
+//! Everything in this module is `no_std` and allocation free, so it can be
+//! used directly on a microcontroller through any [`Transport`].
+
+use crate::transport::Transport;
+
+pub const SERVO_ID_ALL: u8 = 0xfe;
+pub const SERVO_MOVE_TIME_WRITE: u8 = 1;
+pub const SERVO_MOVE_TIME_READ: u8 = 2;
+pub const SERVO_MOVE_TIME_WAIT_WRITE: u8 = 7;
+pub const SERVO_MOVE_TIME_WAIT_READ: u8 = 8;
+pub const SERVO_MOVE_START: u8 = 11;
+pub const SERVO_MOVE_STOP: u8 = 12;
+pub const SERVO_ID_WRITE: u8 = 13;
+pub const SERVO_ID_READ: u8 = 14;
+pub const SERVO_ANGLE_OFFSET_ADJUST: u8 = 17;
+pub const SERVO_ANGLE_OFFSET_WRITE: u8 = 18;
+pub const SERVO_ANGLE_OFFSET_READ: u8 = 19;
+pub const SERVO_ANGLE_LIMIT_WRITE: u8 = 20;
+pub const SERVO_ANGLE_LIMIT_READ: u8 = 21;
+pub const SERVO_VIN_LIMIT_WRITE: u8 = 22;
+pub const SERVO_VIN_LIMIT_READ: u8 = 23;
+pub const SERVO_TEMP_MAX_LIMIT_WRITE: u8 = 24;
+pub const SERVO_TEMP_MAX_LIMIT_READ: u8 = 25;
+pub const SERVO_TEMP_READ: u8 = 26;
+pub const SERVO_VIN_READ: u8 = 27;
+pub const SERVO_POS_READ: u8 = 28;
+pub const SERVO_OR_MOTOR_MODE_WRITE: u8 = 29;
+pub const SERVO_OR_MOTOR_MODE_READ: u8 = 30;
+pub const SERVO_LOAD_OR_UNLOAD_WRITE: u8 = 31;
+pub const SERVO_LOAD_OR_UNLOAD_READ: u8 = 32;
+pub const SERVO_LED_CTRL_WRITE: u8 = 33;
+pub const SERVO_LED_CTRL_READ: u8 = 34;
+pub const SERVO_LED_ERROR_WRITE: u8 = 35;
+pub const SERVO_LED_ERROR_READ: u8 = 36;
+
+pub const SERVO_ERROR_OVER_TEMPERATURE: u8 = 1;
+pub const SERVO_ERROR_OVER_VOLTAGE: u8 = 2;
+pub const SERVO_ERROR_LOCKED_ROTOR: u8 = 4;
+
+/// The read command that returns what `write` stores, if there is one.
+pub fn read_back_command(write: u8) -> Option<u8> {
+    match write {
+        SERVO_MOVE_TIME_WRITE => Some(SERVO_MOVE_TIME_READ),
+        SERVO_MOVE_TIME_WAIT_WRITE => Some(SERVO_MOVE_TIME_WAIT_READ),
+        SERVO_ID_WRITE => Some(SERVO_ID_READ),
+        SERVO_ANGLE_OFFSET_ADJUST => Some(SERVO_ANGLE_OFFSET_READ),
+        SERVO_ANGLE_LIMIT_WRITE => Some(SERVO_ANGLE_LIMIT_READ),
+        SERVO_VIN_LIMIT_WRITE => Some(SERVO_VIN_LIMIT_READ),
+        SERVO_TEMP_MAX_LIMIT_WRITE => Some(SERVO_TEMP_MAX_LIMIT_READ),
+        SERVO_OR_MOTOR_MODE_WRITE => Some(SERVO_OR_MOTOR_MODE_READ),
+        SERVO_LOAD_OR_UNLOAD_WRITE => Some(SERVO_LOAD_OR_UNLOAD_READ),
+        SERVO_LED_CTRL_WRITE => Some(SERVO_LED_CTRL_READ),
+        SERVO_LED_ERROR_WRITE => Some(SERVO_LED_ERROR_READ),
+        _ => None,
+    }
+}
+
+/// Number of params in a servo's reply to read command `read`, `None` for commands that
+/// get no reply.
+pub fn reply_len(read: u8) -> Option<usize> {
+    match read {
+        SERVO_MOVE_TIME_READ | SERVO_MOVE_TIME_WAIT_READ | SERVO_ANGLE_LIMIT_READ | SERVO_VIN_LIMIT_READ
+            | SERVO_OR_MOTOR_MODE_READ => Some(4),
+        SERVO_VIN_READ | SERVO_POS_READ => Some(2),
+        SERVO_ID_READ | SERVO_ANGLE_OFFSET_READ | SERVO_TEMP_MAX_LIMIT_READ | SERVO_TEMP_READ
+            | SERVO_LOAD_OR_UNLOAD_READ | SERVO_LED_CTRL_READ | SERVO_LED_ERROR_READ => Some(1),
+        _ => None,
+    }
+}
+
+/// Protocol name of a command byte, e.g. `28` is `"POS_READ"`, or `"UNKNOWN"`.
+/// [`CommandName`] displays unknown commands with their number.
+pub fn command_name(cmd: u8) -> &'static str {
+    match cmd {
+        SERVO_MOVE_TIME_WRITE => "MOVE_TIME_WRITE",
+        SERVO_MOVE_TIME_READ => "MOVE_TIME_READ",
+        SERVO_MOVE_TIME_WAIT_WRITE => "MOVE_TIME_WAIT_WRITE",
+        SERVO_MOVE_TIME_WAIT_READ => "MOVE_TIME_WAIT_READ",
+        SERVO_MOVE_START => "MOVE_START",
+        SERVO_MOVE_STOP => "MOVE_STOP",
+        SERVO_ID_WRITE => "ID_WRITE",
+        SERVO_ID_READ => "ID_READ",
+        SERVO_ANGLE_OFFSET_ADJUST => "ANGLE_OFFSET_ADJUST",
+        SERVO_ANGLE_OFFSET_WRITE => "ANGLE_OFFSET_WRITE",
+        SERVO_ANGLE_OFFSET_READ => "ANGLE_OFFSET_READ",
+        SERVO_ANGLE_LIMIT_WRITE => "ANGLE_LIMIT_WRITE",
+        SERVO_ANGLE_LIMIT_READ => "ANGLE_LIMIT_READ",
+        SERVO_VIN_LIMIT_WRITE => "VIN_LIMIT_WRITE",
+        SERVO_VIN_LIMIT_READ => "VIN_LIMIT_READ",
+        SERVO_TEMP_MAX_LIMIT_WRITE => "TEMP_MAX_LIMIT_WRITE",
+        SERVO_TEMP_MAX_LIMIT_READ => "TEMP_MAX_LIMIT_READ",
+        SERVO_TEMP_READ => "TEMP_READ",
+        SERVO_VIN_READ => "VIN_READ",
+        SERVO_POS_READ => "POS_READ",
+        SERVO_OR_MOTOR_MODE_WRITE => "OR_MOTOR_MODE_WRITE",
+        SERVO_OR_MOTOR_MODE_READ => "OR_MOTOR_MODE_READ",
+        SERVO_LOAD_OR_UNLOAD_WRITE => "LOAD_OR_UNLOAD_WRITE",
+        SERVO_LOAD_OR_UNLOAD_READ => "LOAD_OR_UNLOAD_READ",
+        SERVO_LED_CTRL_WRITE => "LED_CTRL_WRITE",
+        SERVO_LED_CTRL_READ => "LED_CTRL_READ",
+        SERVO_LED_ERROR_WRITE => "LED_ERROR_WRITE",
+        SERVO_LED_ERROR_READ => "LED_ERROR_READ",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Displays a command byte by name, e.g. `POS_READ`, or `UNKNOWN(99)`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CommandName(pub u8);
+
+impl core::fmt::Display for CommandName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match command_name(self.0) {
+            "UNKNOWN" => write!(f, "UNKNOWN({})", self.0),
+            name => f.write_str(name),
+        }
+    }
+}
+
+/// Longest move time, in milliseconds, the servo accepts.
+pub const MAX_MOVE_TIME_MS: u16 = 30000;
+
+/// Frame header, sent twice at the start of every packet.
+pub const FRAME_HEADER: u8 = 0x55;
+/// Largest parameter block of any LX-16A command.
+pub const MAX_PARAMS: usize = 4;
+/// Header (2) + id + length + command + params + checksum.
+pub const MAX_FRAME_LEN: usize = 6 + MAX_PARAMS;
+
+pub fn lower_byte(value: u16) -> u8 {
+    (value & 0xFF) as u8
+}
+
+pub fn higher_byte(value: u16) -> u8 {
+    ((value >> 8) & 0xFF) as u8
+}
+
+pub fn word(low: u8, high: u8) -> u16 {
+    (low as u16) | ((high as u16) << 8)
+}
+
+/// Checksum over id, length, command and params: the inverted low byte of their sum.
+pub fn checksum(servo_id: u8, length: u8, command: u8, params: &[u8]) -> u8 {
+    let sum = params.iter()
+        .fold(servo_id as u16 + length as u16 + command as u16, |acc, &byte| acc + byte as u16);
+    !(sum as u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// More than [`MAX_PARAMS`] parameters were supplied.
+    TooManyParams(usize),
+    /// The length byte is outside the range any command uses.
+    InvalidLength(u8),
+    /// The checksum byte doesn't match the frame contents.
+    ChecksumMismatch { expected: u8, received: u8 },
+    /// The frame doesn't start with two [`FRAME_HEADER`] bytes.
+    InvalidHeader,
+    /// A well-formed frame that isn't the reply asked for: another servo or command, or
+    /// the wrong number of params.
+    UnexpectedFrame { servo_id: u8, command: u8 },
+}
+
+impl core::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FrameError::TooManyParams(count) => write!(f, "{} params, at most {} fit in a frame", count, MAX_PARAMS),
+            FrameError::InvalidLength(length) => write!(f, "invalid length byte {}", length),
+            FrameError::ChecksumMismatch { expected, received } =>
+                write!(f, "checksum {:02X}, expected {:02X}", received, expected),
+            FrameError::InvalidHeader => write!(f, "missing {:02X} {:02X} header", FRAME_HEADER, FRAME_HEADER),
+            FrameError::UnexpectedFrame { servo_id, command } => write!(f, "unexpected {} frame from servo {}", CommandName(*command), servo_id),
+        }
+    }
+}
+
+/// Bytes of a frame as received, kept for diagnostics.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RawFrame {
+    bytes: [u8; MAX_FRAME_LEN],
+    len: u8,
+}
+
+impl RawFrame {
+    /// Copies up to [`MAX_FRAME_LEN`] bytes.
+    pub fn new(bytes: &[u8]) -> RawFrame {
+        let len = bytes.len().min(MAX_FRAME_LEN);
+        let mut raw = RawFrame { bytes: [0; MAX_FRAME_LEN], len: len as u8 };
+        raw.bytes[..len].copy_from_slice(&bytes[..len]);
+        raw
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl core::fmt::Debug for RawFrame {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "RawFrame({})", HexDump(self.as_bytes()))
+    }
+}
+
+/// Displays bytes as space separated upper case hex, e.g. `55 55 01 03 1C DF`.
+#[derive(Clone, Copy)]
+pub struct HexDump<'a>(pub &'a [u8]);
+
+impl core::fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 { write!(f, " ")?; }
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// [`HexDump`] as a `String`, for logs and error messages.
+#[cfg(feature = "std")]
+pub fn format_packet(bytes: &[u8]) -> String {
+    HexDump(bytes).to_string()
+}
+
+/// Error from a frame read or write over a [`Transport`].
+#[derive(Debug)]
+pub enum Error<E> {
+    Transport(E),
+    /// A frame was received but failed validation.
+    Frame { error: FrameError, raw: RawFrame },
+}
+
+/// A single packet on the bus, in either direction.
+///
+/// `Debug` shows the decoded fields with params in hex rather than the raw buffer.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    pub servo_id: u8,
+    pub command: u8,
+    params: [u8; MAX_PARAMS],
+    params_len: u8,
+}
+
+impl Frame {
+    pub fn new(servo_id: u8, command: u8, params: &[u8]) -> Result<Frame, FrameError> {
+        if params.len() > MAX_PARAMS {
+            return Err(FrameError::TooManyParams(params.len()));
+        }
+
+        let mut frame = Frame { servo_id, command, params: [0; MAX_PARAMS], params_len: params.len() as u8 };
+        frame.params[..params.len()].copy_from_slice(params);
+        Ok(frame)
+    }
+
+    pub fn params(&self) -> &[u8] {
+        &self.params[..self.params_len as usize]
+    }
+
+    /// Value of the length byte: params + length + command + checksum.
+    pub fn length(&self) -> u8 {
+        3 + self.params_len
+    }
+
+    pub fn checksum(&self) -> u8 {
+        checksum(self.servo_id, self.length(), self.command, self.params())
+    }
+
+    /// Writes the wire representation into `buf` and returns the used part.
+    pub fn encode<'a>(&self, buf: &'a mut [u8; MAX_FRAME_LEN]) -> &'a [u8] {
+        let params = self.params();
+        buf[0] = FRAME_HEADER;
+        buf[1] = FRAME_HEADER;
+        buf[2] = self.servo_id;
+        buf[3] = self.length();
+        buf[4] = self.command;
+        buf[5..5 + params.len()].copy_from_slice(params);
+        buf[5 + params.len()] = self.checksum();
+        &buf[..6 + params.len()]
+    }
+
+    /// Parses one complete frame, header included.
+    pub fn decode(bytes: &[u8]) -> Result<Frame, FrameError> {
+        if bytes.len() < 6 {
+            return Err(FrameError::InvalidLength(bytes.len() as u8));
+        }
+        if bytes[..2] != [FRAME_HEADER, FRAME_HEADER] {
+            return Err(FrameError::InvalidHeader);
+        }
+
+        let length = bytes[3];
+        if !(3..=3 + MAX_PARAMS as u8).contains(&length) || bytes.len() != length as usize + 3 {
+            return Err(FrameError::InvalidLength(length));
+        }
+
+        let frame = Frame::new(bytes[2], bytes[4], &bytes[5..bytes.len() - 1])?;
+        let received = bytes[bytes.len() - 1];
+        let expected = frame.checksum();
+        if received != expected {
+            return Err(FrameError::ChecksumMismatch { expected, received });
+        }
+
+        Ok(frame)
+    }
+}
+
+impl core::fmt::Debug for Frame {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Frame {{ servo_id: {}, command: {}, params: [{}] }}", self.servo_id, CommandName(self.command), HexDump(self.params()))
+    }
+}
+
+/// Encodes and writes one frame, flushing so it is on the wire before any reply is read.
+pub fn write_frame<T: Transport + ?Sized>(transport: &mut T, frame: &Frame) -> Result<(), Error<T::Error>> {
+    let mut buf = [0u8; MAX_FRAME_LEN];
+    transport.write_all(frame.encode(&mut buf)).map_err(Error::Transport)?;
+    transport.flush().map_err(Error::Transport)
+}
+
+/// Reads the next frame, skipping bytes until a `0x55 0x55` header with a plausible length.
+///
+/// Once a header and length are in, exactly the rest of the frame is read. `0x55` is a
+/// valid param byte, so a header found mid-stream can be a false sync; if the checksum
+/// fails and another header shows up in the bytes already read, parsing restarts there.
+/// Only a failed frame with no such header is reported as an error.
+pub fn read_frame<T: Transport + ?Sized>(transport: &mut T) -> Result<Frame, Error<T::Error>> {
+    FrameReader::new().read_frame(transport)
+}
+
+// Outcome of FrameReader::step
+pub(crate) enum Step {
+    // The buffer must hold this many bytes before parsing can go on
+    Need(usize),
+    Frame(Frame),
+    Invalid { error: FrameError, raw: RawFrame },
+}
+
+/// [`read_frame`] with a buffer that outlives a single frame.
+///
+/// In single-shot mode each read asks the transport for as much as a whole frame, so a
+/// reply that has already arrived takes one call instead of three. Bytes read past the
+/// end of a frame are kept for the next one, so keep one reader per transport.
+pub struct FrameReader {
+    buf: [u8; MAX_FRAME_LEN],
+    // bytes of `buf` already read but not yet consumed
+    filled: usize,
+    single_shot: bool,
+}
+
+impl FrameReader {
+    /// Reads exactly what each step of parsing needs, never past the end of a frame.
+    pub const fn new() -> Self {
+        FrameReader { buf: [0; MAX_FRAME_LEN], filled: 0, single_shot: false }
+    }
+
+    /// Reads whatever the transport has, up to a full frame, at each step.
+    pub const fn single_shot() -> Self {
+        FrameReader { buf: [0; MAX_FRAME_LEN], filled: 0, single_shot: true }
+    }
+
+    pub fn is_single_shot(&self) -> bool {
+        self.single_shot
+    }
+
+    /// Bytes read but not yet parsed.
+    pub fn buffered(&self) -> usize {
+        self.filled
+    }
+
+    /// Drops buffered bytes, e.g. after the transport was reopened.
+    pub fn clear(&mut self) {
+        self.filled = 0;
+    }
+
+    /// Reads the next frame; see [`read_frame`]. A transport error drops a partly read frame.
+    pub fn read_frame<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<Frame, Error<T::Error>> {
+        let result = self.next_frame(transport);
+        if let Err(Error::Transport(_)) = result {
+            self.clear();
+        }
+        result
+    }
+
+    fn next_frame<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<Frame, Error<T::Error>> {
+        loop
+        {
+            match self.step() {
+                Step::Need(len) => self.fill(transport, len)?,
+                Step::Frame(frame) => return Ok(frame),
+                Step::Invalid { error, raw } => return Err(Error::Frame { error, raw }),
+            }
+        }
+    }
+
+    // Parses what is buffered as far as it goes. Shared with the async controller, which
+    // fills the buffer itself through `unfilled` and `advance`.
+    pub(crate) fn step(&mut self) -> Step {
+        loop
+        {
+            if self.filled < 2 {
+                return Step::Need(2);
+            }
+            if self.buf[0] != FRAME_HEADER || self.buf[1] != FRAME_HEADER {
+                self.discard(1);
+                continue;
+            }
+
+            if self.filled < 5 {
+                return Step::Need(5);
+            }
+
+            let length = self.buf[3] as usize;
+            if !(3..=3 + MAX_PARAMS).contains(&length)
+            {
+                log::error!("Invalid length for packet {:?}", &self.buf[..5]);
+                self.discard(1);
+                continue;
+            }
+
+            // params + checksum
+            if self.filled < length + 3 {
+                return Step::Need(length + 3);
+            }
+
+            let bytes = &self.buf[..length + 3];
+            match Frame::decode(bytes) {
+                Ok(frame) => {
+                    self.discard(length + 3);
+                    return Step::Frame(frame);
+                }
+                Err(error) => match next_header(bytes) {
+                    Some(start) => {
+                        log::warn!("Resyncing after {:?} in {:?}", error, bytes);
+                        self.discard(start);
+                    }
+                    None => {
+                        let raw = RawFrame::new(bytes);
+                        self.discard(length + 3);
+                        return Step::Invalid { error, raw };
+                    }
+                },
+            }
+        }
+    }
+
+    // The part of the buffer still to be read for `step` to get past a `Need(len)`
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    pub(crate) fn unfilled(&mut self, len: usize) -> &mut [u8] {
+        &mut self.buf[self.filled.min(len)..len]
+    }
+
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    pub(crate) fn advance(&mut self, count: usize) {
+        self.filled = (self.filled + count).min(MAX_FRAME_LEN);
+    }
+
+    // Reads until the first `len` bytes of `buf` are present
+    fn fill<T: Transport + ?Sized>(&mut self, transport: &mut T, len: usize) -> Result<(), Error<T::Error>> {
+        if self.filled < len {
+            if self.single_shot {
+                // A transport returning more than fits, or less than asked, mustn't corrupt the count
+                let read = transport.read_some(&mut self.buf[self.filled..], len - self.filled).map_err(Error::Transport)?;
+                self.filled += read.min(MAX_FRAME_LEN - self.filled);
+            }
+            if self.filled < len {
+                transport.read_exact(&mut self.buf[self.filled..len]).map_err(Error::Transport)?;
+                self.filled = len;
+            }
+        }
+        Ok(())
+    }
+
+    fn discard(&mut self, count: usize) {
+        self.buf.copy_within(count..self.filled, 0);
+        self.filled -= count;
+    }
+}
+
+impl Default for FrameReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Start of a possible header after the first byte; a trailing lone 0x55 counts
+fn next_header(bytes: &[u8]) -> Option<usize> {
+    (1..bytes.len()).find(|&i| bytes[i] == FRAME_HEADER && bytes.get(i + 1).copied().unwrap_or(FRAME_HEADER) == FRAME_HEADER)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    // Name, id, command, params, bytes on the wire
+    type Golden = (&'static str, u8, u8, &'static [u8], &'static [u8]);
+
+    // Frames as laid out in the LX-16A bus protocol document
+    const GOLDEN: &[Golden] = &[
+        ("move servo 1 to 1000 over 1000 ms", 1, SERVO_MOVE_TIME_WRITE, &[0xE8, 0x03, 0xE8, 0x03], &[0x55, 0x55, 0x01, 0x07, 0x01, 0xE8, 0x03, 0xE8, 0x03, 0x20]),
+        ("move servo 1 to 500 at once", 1, SERVO_MOVE_TIME_WRITE, &[0xF4, 0x01, 0x00, 0x00], &[0x55, 0x55, 0x01, 0x07, 0x01, 0xF4, 0x01, 0x00, 0x00, 0x01]),
+        ("read the position of servo 1", 1, SERVO_POS_READ, &[], &[0x55, 0x55, 0x01, 0x03, 0x1C, 0xDF]),
+        ("position reply of 500", 1, SERVO_POS_READ, &[0xF4, 0x01], &[0x55, 0x55, 0x01, 0x05, 0x1C, 0xF4, 0x01, 0xE8]),
+        ("broadcast id read", SERVO_ID_ALL, SERVO_ID_READ, &[], &[0x55, 0x55, 0xFE, 0x03, 0x0E, 0xF0]),
+        ("id reply from servo 1", 1, SERVO_ID_READ, &[0x01], &[0x55, 0x55, 0x01, 0x04, 0x0E, 0x01, 0xEB]),
+        ("change the id of servo 1 to 2", 1, SERVO_ID_WRITE, &[0x02], &[0x55, 0x55, 0x01, 0x04, 0x0D, 0x02, 0xEB]),
+        ("stop every servo", SERVO_ID_ALL, SERVO_MOVE_STOP, &[], &[0x55, 0x55, 0xFE, 0x03, 0x0C, 0xF2]),
+        ("unload servo 1", 1, SERVO_LOAD_OR_UNLOAD_WRITE, &[0x00], &[0x55, 0x55, 0x01, 0x04, 0x1F, 0x00, 0xDB]),
+        ("motor mode at speed -500", 1, SERVO_OR_MOTOR_MODE_WRITE, &[0x01, 0x00, 0x0C, 0xFE], &[0x55, 0x55, 0x01, 0x07, 0x1D, 0x01, 0x00, 0x0C, 0xFE, 0xCF]),
+        ("temperature reply of 40 C", 1, SERVO_TEMP_READ, &[0x28], &[0x55, 0x55, 0x01, 0x04, 0x1A, 0x28, 0xB8]),
+        ("voltage reply of 7400 mV", 1, SERVO_VIN_READ, &[0xE8, 0x1C], &[0x55, 0x55, 0x01, 0x05, 0x1B, 0xE8, 0x1C, 0xDA]),
+        ("angle limits 0..1000", 1, SERVO_ANGLE_LIMIT_WRITE, &[0x00, 0x00, 0xE8, 0x03], &[0x55, 0x55, 0x01, 0x07, 0x14, 0x00, 0x00, 0xE8, 0x03, 0xF8]),
+    ];
+
+    #[test]
+    fn encoding_matches_the_documented_bytes() {
+        for &(name, servo_id, command, params, wire) in GOLDEN {
+            let frame = Frame::new(servo_id, command, params).unwrap();
+            let mut buf = [0u8; MAX_FRAME_LEN];
+            assert_eq!(frame.encode(&mut buf), wire, "{}", name);
+        }
+    }
+
+    #[test]
+    fn decoding_the_documented_bytes_recovers_the_frame() {
+        for &(name, servo_id, command, params, wire) in GOLDEN {
+            let frame = Frame::decode(wire).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!((frame.servo_id, frame.command, frame.params()), (servo_id, command, params), "{}", name);
+            assert_eq!(frame.length(), wire[3], "{}", name);
+        }
+    }
+
+    #[test]
+    fn reading_the_documented_bytes_recovers_the_frame() {
+        let stream: Vec<u8> = GOLDEN.iter().flat_map(|&(_, _, _, _, wire)| wire.iter().copied()).collect();
+        let mut transport = Bytes(&stream);
+        for &(name, servo_id, command, params, _) in GOLDEN {
+            let frame = read_frame(&mut transport).unwrap_or_else(|_| panic!("{}: not read", name));
+            assert_eq!((frame.servo_id, frame.command, frame.params()), (servo_id, command, params), "{}", name);
+        }
+    }
+
+    #[test]
+    fn checksum_examples() {
+        // Sum fits a byte: 0x01 + 0x03 + 0x1C = 0x20, inverted
+        assert_eq!(checksum(0x01, 0x03, 0x1C, &[]), 0xDF);
+        // Sum overflows: 0x1DF keeps its low byte before inverting
+        assert_eq!(checksum(0x01, 0x07, 0x01, &[0xE8, 0x03, 0xE8, 0x03]), 0x20);
+        assert_eq!(checksum(0xFE, 0x03, 0x0C, &[]), 0xF2);
+        assert_eq!(checksum(0xFF, 0xFF, 0xFF, &[0xFF; MAX_PARAMS]), !(0xFFu16 * 7) as u8);
+    }
+
+    #[test]
+    fn a_wrong_checksum_is_rejected() {
+        let bytes = [0x55, 0x55, 0x01, 0x03, 0x1C, 0xDE];
+        assert_eq!(Frame::decode(&bytes), Err(FrameError::ChecksumMismatch { expected: 0xDF, received: 0xDE }));
+    }
+
+    fn frame() -> impl Strategy<Value = Frame> {
+        (any::<u8>(), any::<u8>(), proptest::collection::vec(any::<u8>(), 0..=MAX_PARAMS))
+            .prop_map(|(servo_id, command, params)| Frame::new(servo_id, command, &params).unwrap())
+    }
+
+    fn wire(frame: &Frame) -> Vec<u8> {
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        frame.encode(&mut buf).to_vec()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(512))]
+
+        #[test]
+        fn decode_inverts_encode(frame in frame()) {
+            prop_assert_eq!(Frame::decode(&wire(&frame)), Ok(frame));
+        }
+
+        // Noise before the frame holds no header byte, so nothing in it can pass for a frame
+        #[test]
+        fn a_frame_in_noise_is_found(
+            frame in frame(),
+            before in proptest::collection::vec(any::<u8>().prop_filter("header byte", |&b| b != FRAME_HEADER), 0..32),
+            after in proptest::collection::vec(any::<u8>(), 0..32),
+            single_shot in any::<bool>(),
+        ) {
+            let stream: Vec<u8> = before.iter().chain(&wire(&frame)).chain(&after).copied().collect();
+            let mut reader = if single_shot { FrameReader::single_shot() } else { FrameReader::new() };
+            prop_assert_eq!(reader.read_frame(&mut Bytes(&stream)).ok(), Some(frame));
+        }
+
+        #[test]
+        fn a_corrupted_byte_is_never_decoded(frame in frame(), index in any::<prop::sample::Index>(), flip in 1..=255u8) {
+            let mut bytes = wire(&frame);
+            let index = index.index(bytes.len());
+            bytes[index] ^= flip;
+            prop_assert!(Frame::decode(&bytes).is_err(), "byte {} flipped by {:02X} decoded", index, flip);
+        }
+    }
+
+    // Found by the frame_reader fuzz target: a single-shot reader given fewer bytes
+    // than it asked for underflowed when discarding the frame
+    #[test]
+    fn a_short_read_some_still_yields_whole_frames() {
+        let stream: Vec<u8> = GOLDEN.iter().flat_map(|&(_, _, _, _, wire)| wire.iter().copied()).collect();
+        for chunk in 1..=MAX_FRAME_LEN {
+            let mut transport = Chunked { data: &stream, chunk, overstate: 0 };
+            let mut reader = FrameReader::single_shot();
+            for &(name, servo_id, command, params, _) in GOLDEN {
+                let frame = reader.read_frame(&mut transport).unwrap_or_else(|_| panic!("{} in chunks of {}: not read", name, chunk));
+                assert_eq!((frame.servo_id, frame.command, frame.params()), (servo_id, command, params), "{}", name);
+            }
+            assert!(matches!(reader.read_frame(&mut transport), Err(Error::Transport(()))));
+        }
+    }
+
+    #[test]
+    fn a_read_some_claiming_more_than_fits_does_not_overrun_the_buffer() {
+        let mut stream = vec![0x00, FRAME_HEADER];
+        stream.extend_from_slice(&wire(&Frame::new(1, SERVO_POS_READ, &[0xF4, 0x01]).unwrap()));
+        let mut transport = Chunked { data: &stream, chunk: MAX_FRAME_LEN, overstate: 100 };
+        let mut reader = FrameReader::single_shot();
+        let _ = reader.read_frame(&mut transport);
+        assert!(reader.buffered() <= MAX_FRAME_LEN);
+    }
+
+    // The tail of a stream cut off mid-frame, in every reading mode, ends in a transport
+    // error rather than a panic or a frame
+    #[test]
+    fn truncated_input_ends_with_a_transport_error() {
+        let wire = wire(&Frame::new(1, SERVO_MOVE_TIME_WRITE, &[0xE8, 0x03, 0xE8, 0x03]).unwrap());
+        for len in 0..wire.len() {
+            for chunk in [1, 3, MAX_FRAME_LEN] {
+                let mut transport = Chunked { data: &wire[..len], chunk, overstate: 0 };
+                assert!(matches!(FrameReader::single_shot().read_frame(&mut transport), Err(Error::Transport(()))), "{} bytes", len);
+            }
+            assert!(matches!(read_frame(&mut Bytes(&wire[..len])), Err(Error::Transport(()))), "{} bytes", len);
+        }
+    }
+
+    // Serves `data` at most `chunk` bytes per call like the fuzz target's transport,
+    // with read_some returning fewer than asked and adding `overstate` to its count
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        overstate: usize,
+    }
+
+    impl Chunked<'_> {
+        fn take(&mut self, buf: &mut [u8]) -> usize {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            n
+        }
+    }
+
+    impl Transport for Chunked<'_> {
+        type Error = ();
+
+        fn write_all(&mut self, _bytes: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ()> {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.take(&mut buf[filled..]) {
+                    0 => return Err(()),
+                    n => filled += n,
+                }
+            }
+            Ok(())
+        }
+
+        fn read_some(&mut self, buf: &mut [u8], _min: usize) -> Result<usize, ()> {
+            match self.take(buf) {
+                0 => Err(()),
+                n => Ok(n + self.overstate),
+            }
+        }
+    }
+
+    // Serves a fixed byte string, then fails as if timed out
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Transport for Bytes<'_> {
+        type Error = ();
+
+        fn write_all(&mut self, _bytes: &[u8]) -> Result<(), ()> {
+            Ok(())
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ()> {
+            if self.0.len() < buf.len() {
+                self.0 = &[];
+                return Err(());
+            }
+            let (head, rest) = self.0.split_at(buf.len());
+            buf.copy_from_slice(head);
+            self.0 = rest;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Hooks for observing traffic on the bus: raw bytes and query timing.
+
+use std::{io, time::Duration};
+
+use crate::id::ServoId;
+use crate::transport::Transport;
+
+/// Which way a tapped buffer went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Written to the bus.
+    Tx,
+    /// Read from the bus.
+    Rx,
+}
+
+/// Callback set with `ServoController::set_packet_tap`.
+pub type PacketTap = Box<dyn Fn(Direction, &[u8]) + Send>;
+
+/// Callback set with `ServoController::set_latency_hook`: servo, command, round trip.
+pub type LatencyHook = Box<dyn Fn(ServoId, u8, Duration) + Send>;
+
+// Reports everything passing through to the tap; reads include bytes skipped while resyncing
+pub(crate) struct Tapped<'a, T: ?Sized> {
+    pub(crate) inner: &'a mut T,
+    pub(crate) tap: &'a PacketTap,
+}
+
+impl<T: Transport<Error = io::Error> + ?Sized> Transport for Tapped<'_, T> {
+    type Error = io::Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.inner.write_all(bytes)?;
+        (self.tap)(Direction::Tx, bytes);
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read_exact(buf)?;
+        (self.tap)(Direction::Rx, buf);
+        Ok(())
+    }
+
+    fn read_some(&mut self, buf: &mut [u8], min: usize) -> Result<usize, Self::Error> {
+        let read = self.inner.read_some(buf, min)?;
+        (self.tap)(Direction::Rx, &buf[..read]);
+        Ok(read)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn bytes_to_read(&mut self) -> Result<Option<usize>, Self::Error> {
+        self.inner.bytes_to_read()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.inner.timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        self.inner.set_timeout(timeout)
+    }
+}
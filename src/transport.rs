@@ -0,0 +1,84 @@
+//! Byte-level link between the host and the servo bus.
+
+use core::time::Duration;
+
+/// A half-duplex serial link the protocol can be driven over.
+///
+/// Timeout handling is optional: transports without a notion of time keep the
+/// default no-op implementations.
+pub trait Transport {
+    type Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error>;
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Reads at least `min` bytes into `buf`, and more if they are already waiting, up to
+    /// `buf.len()`; returns how many were read. The default reads exactly `min`.
+    fn read_some(&mut self, buf: &mut [u8], min: usize) -> Result<usize, Self::Error> {
+        self.read_exact(&mut buf[..min])?;
+        Ok(min)
+    }
+
+    /// Pushes buffered output onto the wire. Transports that write straight through
+    /// keep the default no-op.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Bytes received and waiting to be read, or `None` for transports that can't tell.
+    fn bytes_to_read(&mut self) -> Result<Option<usize>, Self::Error> {
+        Ok(None)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Transport for Box<dyn serialport::SerialPort> {
+    type Error = std::io::Error;
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
+        std::io::Write::write_all(self, bytes)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        std::io::Read::read_exact(self, buf)
+    }
+
+    fn read_some(&mut self, buf: &mut [u8], min: usize) -> Result<usize, Self::Error> {
+        let mut read = 0;
+        while read < min {
+            match std::io::Read::read(self, &mut buf[read..]) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(read)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        std::io::Write::flush(self)
+    }
+
+    fn bytes_to_read(&mut self) -> Result<Option<usize>, Self::Error> {
+        Ok(Some(serialport::SerialPort::bytes_to_read(self.as_ref())? as usize))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        Some(serialport::SerialPort::timeout(self.as_ref()))
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        serialport::SerialPort::set_timeout(self.as_mut(), timeout)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! Plain data types read from and written to servos.
+
+use crate::id::ServoId;
+use crate::protocol::{SERVO_ERROR_LOCKED_ROTOR, SERVO_ERROR_OVER_TEMPERATURE, SERVO_ERROR_OVER_VOLTAGE};
+use core::fmt;
+
+use crate::units::{Celsius, Millivolts, Position};
+
+/// Hardware travel limits; the servo refuses to move outside them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AngleLimit {
+    pub min: Position,
+    pub max: Position,
+}
+
+/// Operating mode of a servo, as written by `SERVO_OR_MOTOR_MODE_WRITE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// Normal positional control.
+    Position,
+    /// Continuous rotation; `speed` in -1000..=1000, negative turns backwards.
+    Motor { speed: i16 },
+}
+
+impl Mode {
+    pub const MAX_SPEED: i16 = 1000;
+
+    /// Mode, reserved byte and speed word, as sent on the wire.
+    pub fn to_params(self) -> [u8; 4] {
+        let speed = match self {
+            Mode::Position => 0,
+            Mode::Motor { speed } => speed as u16,
+        };
+        [matches!(self, Mode::Motor { .. }) as u8, 0, (speed & 0xFF) as u8, (speed >> 8) as u8]
+    }
+
+    /// Decodes a mode read reply; `None` for an unknown mode byte or short params.
+    pub fn from_params(params: &[u8]) -> Option<Mode> {
+        match params {
+            [0, _, _, _] => Some(Mode::Position),
+            [1, _, low, high] => Some(Mode::Motor { speed: (*low as u16 | (*high as u16) << 8) as i16 }),
+            _ => None,
+        }
+    }
+
+    /// `false` for a motor speed outside -1000..=1000.
+    pub fn is_valid(self) -> bool {
+        match self {
+            Mode::Position => true,
+            Mode::Motor { speed } => (-Self::MAX_SPEED..=Self::MAX_SPEED).contains(&speed),
+        }
+    }
+}
+
+/// `position`, or `motor(speed=N)`.
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Position => write!(f, "position"),
+            Mode::Motor { speed } => write!(f, "motor(speed={})", speed),
+        }
+    }
+}
+
+bitflags::bitflags! {
+    /// Fault bits of the LED error field (`SERVO_LED_ERROR_READ`).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct ServoFault: u8 {
+        const OVER_TEMPERATURE = SERVO_ERROR_OVER_TEMPERATURE;
+        const OVER_VOLTAGE = SERVO_ERROR_OVER_VOLTAGE;
+        const LOCKED_ROTOR = SERVO_ERROR_LOCKED_ROTOR;
+    }
+}
+
+impl ServoFault {
+    pub fn is_over_temp(self) -> bool {
+        self.contains(ServoFault::OVER_TEMPERATURE)
+    }
+
+    pub fn is_over_voltage(self) -> bool {
+        self.contains(ServoFault::OVER_VOLTAGE)
+    }
+
+    pub fn is_locked_rotor(self) -> bool {
+        self.contains(ServoFault::LOCKED_ROTOR)
+    }
+}
+
+/// Comma separated fault names, or `none`.
+impl fmt::Display for ServoFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+
+        let names = [
+            (ServoFault::OVER_TEMPERATURE, "over-temperature"),
+            (ServoFault::OVER_VOLTAGE, "over-voltage"),
+            (ServoFault::LOCKED_ROTOR, "locked-rotor"),
+        ];
+        let mut first = true;
+        for (_, name) in names.iter().filter(|(fault, _)| self.contains(*fault)) {
+            if !first { write!(f, ",")?; }
+            write!(f, "{}", name)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// The persistent settings of one servo, see `ServoController::read_config` and
+/// `ServoController::apply_config`. Change `servo_id` to copy one servo's settings onto another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServoConfig {
+    pub servo_id: ServoId,
+    pub angle_limit: AngleLimit,
+    pub voltage_min: Millivolts,
+    pub voltage_max: Millivolts,
+    pub temperature_limit: Celsius,
+    /// Zero offset in ticks, -125..=125.
+    pub angle_offset: i8,
+}
+
+/// Result of `ServoController::get_position_averaged`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AveragedPosition {
+    /// Mean of the kept samples, rounded to the nearest tick.
+    pub position: Position,
+    /// Largest minus smallest kept sample, in ticks; a wide spread means an unsteady joint.
+    pub spread: u16,
+    /// Samples the mean is taken over.
+    pub kept: usize,
+    /// Samples dropped as outliers.
+    pub rejected: usize,
+    /// Samples whose query failed.
+    pub failed: usize,
+}
+
+/// Snapshot of one servo, see `ServoController::get_status`.
+///
+/// Each field is `None` when its query failed, so one flaky read doesn't hide the rest.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ServoStatus {
+    pub servo_id: ServoId,
+    pub position: Option<Position>,
+    pub temperature: Option<Celsius>,
+    pub voltage: Option<Millivolts>,
+    pub faults: Option<ServoFault>,
+    pub mode: Option<Mode>,
+    pub torque_enabled: Option<bool>,
+}
+
+impl ServoStatus {
+    /// `true` when every query succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.position.is_some() && self.temperature.is_some() && self.voltage.is_some()
+            && self.faults.is_some() && self.mode.is_some() && self.torque_enabled.is_some()
+    }
+}
+
+/// One line of `key=value` pairs, `?` for fields that couldn't be read:
+/// `id=3 pos=512 (122.9°) temp=47°C vin=7.41V mode=position torque=on faults=none`.
+impl fmt::Display for ServoStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id={}", self.servo_id)?;
+        match self.position {
+            Some(position) => write!(f, " pos={} ({:.1}°)", position.as_ticks(), position.as_degrees())?,
+            None => write!(f, " pos=?")?,
+        }
+        match self.temperature {
+            Some(temperature) => write!(f, " temp={}°C", temperature.as_celsius())?,
+            None => write!(f, " temp=?")?,
+        }
+        match self.voltage {
+            Some(voltage) => write!(f, " vin={:.2}V", voltage.as_volts())?,
+            None => write!(f, " vin=?")?,
+        }
+        match self.mode {
+            Some(mode) => write!(f, " mode={}", mode)?,
+            None => write!(f, " mode=?")?,
+        }
+        match self.torque_enabled {
+            Some(enabled) => write!(f, " torque={}", if enabled { "on" } else { "off" })?,
+            None => write!(f, " torque=?")?,
+        }
+        match self.faults {
+            Some(faults) => write!(f, " faults={}", faults),
+            None => write!(f, " faults=?"),
+        }
+    }
+}
+
+/// What a servo is doing, as judged by `ServoController::motion_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionState {
+    /// Position changed by more than the tolerance between samples.
+    Moving,
+    /// Still, within tolerance of the last commanded target.
+    Settled,
+    /// Still, but short of the target: blocked, or pushed off it by a load.
+    Stalled { target: Position, position: Position },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_decode_what_they_encode() {
+        for mode in [Mode::Position, Mode::Motor { speed: 0 }, Mode::Motor { speed: 1 }, Mode::Motor { speed: -1 },
+            Mode::Motor { speed: 1000 }, Mode::Motor { speed: -1000 }, Mode::Motor { speed: -500 }]
+        {
+            assert_eq!(Mode::from_params(&mode.to_params()), Some(mode), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn mode_encoding_matches_the_protocol() {
+        assert_eq!(Mode::Position.to_params(), [0, 0, 0, 0]);
+        assert_eq!(Mode::Motor { speed: 500 }.to_params(), [1, 0, 0xF4, 0x01]);
+        assert_eq!(Mode::Motor { speed: -500 }.to_params(), [1, 0, 0x0C, 0xFE]);
+    }
+
+    #[test]
+    fn unknown_mode_bytes_and_short_params_do_not_decode() {
+        assert_eq!(Mode::from_params(&[2, 0, 0, 0]), None);
+        assert_eq!(Mode::from_params(&[1, 0, 0]), None);
+        assert_eq!(Mode::from_params(&[]), None);
+        // Position mode ignores whatever speed is left in the register
+        assert_eq!(Mode::from_params(&[0, 0, 0xF4, 0x01]), Some(Mode::Position));
+    }
+
+    #[test]
+    fn motor_speeds_past_1000_are_invalid() {
+        assert!(Mode::Motor { speed: 1000 }.is_valid());
+        assert!(Mode::Motor { speed: -1000 }.is_valid());
+        assert!(!Mode::Motor { speed: 1001 }.is_valid());
+        assert!(!Mode::Motor { speed: i16::MIN }.is_valid());
+        assert!(Mode::Position.is_valid());
+    }
+
+    #[test]
+    fn modes_display_by_name() {
+        assert_eq!(Mode::Position.to_string(), "position");
+        assert_eq!(Mode::Motor { speed: -300 }.to_string(), "motor(speed=-300)");
+    }
+
+    #[test]
+    fn faults_display_as_a_list_or_none() {
+        assert_eq!(ServoFault::empty().to_string(), "none");
+        assert_eq!(ServoFault::LOCKED_ROTOR.to_string(), "locked-rotor");
+        assert_eq!((ServoFault::LOCKED_ROTOR | ServoFault::OVER_TEMPERATURE).to_string(), "over-temperature,locked-rotor");
+        assert_eq!(ServoFault::all().to_string(), "over-temperature,over-voltage,locked-rotor");
+    }
+
+    fn status() -> ServoStatus {
+        ServoStatus {
+            servo_id: ServoId::from(3),
+            position: Some(Position::from(512u16)),
+            temperature: Some(Celsius(47)),
+            voltage: Some(Millivolts(7410)),
+            faults: Some(ServoFault::empty()),
+            mode: Some(Mode::Position),
+            torque_enabled: Some(true),
+        }
+    }
+
+    #[test]
+    fn status_displays_on_one_line() {
+        assert_eq!(status().to_string(), "id=3 pos=512 (122.9°) temp=47°C vin=7.41V mode=position torque=on faults=none");
+    }
+
+    #[test]
+    fn status_marks_unread_fields() {
+        let status = ServoStatus { position: None, voltage: None, faults: None, torque_enabled: Some(false), ..status() };
+        assert_eq!(status.to_string(), "id=3 pos=? temp=47°C vin=? mode=position torque=off faults=?");
+        assert!(!status.is_complete());
+    }
+
+    #[test]
+    fn debug_stays_the_raw_representation() {
+        assert_eq!(format!("{:?}", Mode::Motor { speed: 5 }), "Motor { speed: 5 }");
+        assert!(format!("{:?}", status()).starts_with("ServoStatus { servo_id: "));
+    }
+}
@@ -0,0 +1,413 @@
+//! Conversions between raw position ticks and physical angles, and typed
+//! voltage and temperature readings.
+//!
+//! The LX-16A maps 0..=1000 ticks onto 0..=240°, 0.24° per tick. Radian
+//! conversions go through degrees, so both agree on every tick value.
+
+/// Angle covered by one position tick.
+pub const DEGREES_PER_TICK: f32 = 0.24;
+/// Highest commandable position.
+pub const MAX_TICKS: u16 = 1000;
+/// Angle at [`MAX_TICKS`].
+pub const MAX_DEGREES: f32 = 240.0;
+pub const TICKS_PER_DEGREE: f32 = 1.0 / DEGREES_PER_TICK;
+pub const RADIANS_PER_TICK: f32 = DEGREES_PER_TICK * core::f32::consts::PI / 180.0;
+pub const TICKS_PER_RADIAN: f32 = 1.0 / RADIANS_PER_TICK;
+/// [`MAX_DEGREES`] in radians.
+pub const MAX_RADIANS: f32 = MAX_DEGREES * core::f32::consts::PI / 180.0;
+
+// f32::round isn't available in core
+fn round(value: f32) -> f32 {
+    if value >= 0.0 { (value + 0.5) as i32 as f32 } else { (value - 0.5) as i32 as f32 }
+}
+
+pub fn ticks_to_degrees(ticks: i16) -> f32 {
+    ticks as f32 * DEGREES_PER_TICK
+}
+
+/// Nearest tick for `degrees`, without range checking.
+pub fn degrees_to_ticks(degrees: f32) -> i16 {
+    round(degrees / DEGREES_PER_TICK) as i16
+}
+
+/// Commandable position for `degrees`, or `None` outside 0..=240° (or NaN).
+pub fn degrees_to_position(degrees: f32) -> Option<u16> {
+    if (0.0..=MAX_DEGREES).contains(&degrees) {
+        Some(degrees_to_ticks(degrees).clamp(0, MAX_TICKS as i16) as u16)
+    } else {
+        None
+    }
+}
+
+/// Motor-mode speed (-1000..=1000) for `rpm`, scaled so `max_rpm` is full speed and
+/// clamped at both ends. Meaningless unless `max_rpm` is positive.
+pub fn rpm_to_motor_speed(rpm: f32, max_rpm: f32) -> i16 {
+    let speed = round(rpm / max_rpm * 1000.0);
+    if speed.is_nan() { 0 } else { speed.clamp(-1000.0, 1000.0) as i16 }
+}
+
+/// Wheel rpm for a motor-mode `speed`, the inverse of [`rpm_to_motor_speed`].
+pub fn motor_speed_to_rpm(speed: i16, max_rpm: f32) -> f32 {
+    speed as f32 / 1000.0 * max_rpm
+}
+
+pub fn ticks_to_radians(ticks: i16) -> f32 {
+    ticks_to_degrees(ticks).to_radians()
+}
+
+/// Nearest tick for `radians`. Goes through degrees so both paths agree on rounding.
+pub fn radians_to_ticks(radians: f32) -> i16 {
+    degrees_to_ticks(radians.to_degrees())
+}
+
+/// Commandable position for `radians`, or `None` outside 0..=[`MAX_RADIANS`] (or NaN).
+pub fn radians_to_position(radians: f32) -> Option<u16> {
+    if (0.0..=MAX_RADIANS).contains(&radians) {
+        Some(radians_to_ticks(radians).clamp(0, MAX_TICKS as i16) as u16)
+    } else {
+        None
+    }
+}
+
+/// A servo position in ticks.
+///
+/// Readings are signed because a back-driven horn or a servo in motor mode can report
+/// slightly below 0 or above 1000; commands must be inside 0..=1000. Both live in the
+/// same type and [`to_wire`](Position::to_wire) does the check when encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Position(i16);
+
+impl Position {
+    pub const MIN: Position = Position(0);
+    pub const CENTER: Position = Position(500);
+    pub const MAX: Position = Position(MAX_TICKS as i16);
+
+    pub const fn from_ticks(ticks: i16) -> Position {
+        Position(ticks)
+    }
+
+    /// Unsigned ticks, from before positions were signed; saturates like `From<u16>`.
+    #[deprecated(since = "0.2.0", note = "use `Position::from_ticks` or `Position::from`")]
+    pub const fn from_units(units: u16) -> Position {
+        Position(if units > i16::MAX as u16 { i16::MAX } else { units as i16 })
+    }
+
+    /// Rounds to the nearest tick.
+    pub fn from_degrees(degrees: f32) -> Position {
+        Position(degrees_to_ticks(degrees))
+    }
+
+    /// Rounds to the nearest tick.
+    pub fn from_radians(radians: f32) -> Position {
+        Position(radians_to_ticks(radians))
+    }
+
+    /// Decodes the position word of a `SERVO_POS_READ` reply as two's complement, so
+    /// `0xFFFF` is -1. No clamping.
+    pub const fn from_wire(word: u16) -> Position {
+        Position(word as i16)
+    }
+
+    pub const fn as_ticks(self) -> i16 {
+        self.0
+    }
+
+    /// Unsigned ticks, from before positions were signed; readings below 0 come back as 0.
+    #[deprecated(since = "0.2.0", note = "use `Position::as_ticks`")]
+    pub const fn as_units(self) -> u16 {
+        if self.0 < 0 { 0 } else { self.0 as u16 }
+    }
+
+    pub fn as_degrees(self) -> f32 {
+        ticks_to_degrees(self.0)
+    }
+
+    pub fn as_radians(self) -> f32 {
+        ticks_to_radians(self.0)
+    }
+
+    /// Encoding for move commands, `None` outside 0..=1000.
+    pub fn to_wire(self) -> Option<u16> {
+        if self.is_commandable() { Some(self.0 as u16) } else { None }
+    }
+
+    pub fn is_commandable(self) -> bool {
+        (0..=MAX_TICKS as i16).contains(&self.0)
+    }
+
+    /// Nearest commandable position.
+    pub fn clamped(self) -> Position {
+        Position(self.0.clamp(0, MAX_TICKS as i16))
+    }
+}
+
+/// Raw ticks; the backward compatible way to pass a position.
+impl From<u16> for Position {
+    fn from(ticks: u16) -> Position {
+        Position(ticks.min(i16::MAX as u16) as i16)
+    }
+}
+
+impl From<Position> for i16 {
+    fn from(position: Position) -> i16 {
+        position.0
+    }
+}
+
+impl core::ops::Add<i16> for Position {
+    type Output = Position;
+
+    fn add(self, ticks: i16) -> Position {
+        Position(self.0.saturating_add(ticks))
+    }
+}
+
+impl core::ops::Sub<i16> for Position {
+    type Output = Position;
+
+    fn sub(self, ticks: i16) -> Position {
+        Position(self.0.saturating_sub(ticks))
+    }
+}
+
+/// Signed distance in ticks.
+impl core::ops::Sub for Position {
+    type Output = i16;
+
+    fn sub(self, other: Position) -> i16 {
+        self.0.saturating_sub(other.0)
+    }
+}
+
+/// A voltage in millivolts, the unit the servo reports and takes limits in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Millivolts(pub u16);
+
+impl Millivolts {
+    pub const fn as_millivolts(self) -> u16 {
+        self.0
+    }
+
+    pub fn as_volts(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+impl From<u16> for Millivolts {
+    fn from(millivolts: u16) -> Millivolts {
+        Millivolts(millivolts)
+    }
+}
+
+impl From<Millivolts> for u16 {
+    fn from(voltage: Millivolts) -> u16 {
+        voltage.0
+    }
+}
+
+/// Compares against a threshold in millivolts, e.g. `voltage < 6500`.
+impl PartialEq<u16> for Millivolts {
+    fn eq(&self, millivolts: &u16) -> bool {
+        self.0 == *millivolts
+    }
+}
+
+impl PartialOrd<u16> for Millivolts {
+    fn partial_cmp(&self, millivolts: &u16) -> Option<core::cmp::Ordering> {
+        self.0.partial_cmp(millivolts)
+    }
+}
+
+/// Formats as volts with two decimals, e.g. `7.42 V`.
+impl core::fmt::Display for Millivolts {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:.2} V", self.as_volts())
+    }
+}
+
+/// A temperature in whole degrees Celsius, as the servo reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Celsius(pub u8);
+
+impl Celsius {
+    pub const fn as_celsius(self) -> u8 {
+        self.0
+    }
+}
+
+impl From<u8> for Celsius {
+    fn from(celsius: u8) -> Celsius {
+        Celsius(celsius)
+    }
+}
+
+impl From<Celsius> for u8 {
+    fn from(temperature: Celsius) -> u8 {
+        temperature.0
+    }
+}
+
+impl PartialEq<u8> for Celsius {
+    fn eq(&self, celsius: &u8) -> bool {
+        self.0 == *celsius
+    }
+}
+
+impl PartialOrd<u8> for Celsius {
+    fn partial_cmp(&self, celsius: &u8) -> Option<core::cmp::Ordering> {
+        self.0.partial_cmp(celsius)
+    }
+}
+
+impl core::fmt::Display for Celsius {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} °C", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrees_at_the_ends_and_the_middle() {
+        assert_eq!(degrees_to_position(0.0), Some(0));
+        assert_eq!(degrees_to_position(120.0), Some(500));
+        assert_eq!(degrees_to_position(240.0), Some(1000));
+        assert_eq!(ticks_to_degrees(500), 120.0);
+        assert_eq!(ticks_to_degrees(1000), 240.0);
+    }
+
+    #[test]
+    fn degrees_just_outside_the_range_are_refused() {
+        assert_eq!(degrees_to_position(-0.01), None);
+        assert_eq!(degrees_to_position(240.01), None);
+        assert_eq!(degrees_to_position(f32::NAN), None);
+        // Unchecked conversions still round to the nearest tick
+        assert_eq!(degrees_to_ticks(-0.24), -1);
+        assert_eq!(degrees_to_ticks(240.24), 1001);
+    }
+
+    #[test]
+    fn ticks_round_trip_through_degrees() {
+        for ticks in 0..=MAX_TICKS as i16 {
+            assert_eq!(degrees_to_ticks(ticks_to_degrees(ticks)), ticks);
+            assert_eq!(Position::from_degrees(Position::from_ticks(ticks).as_degrees()).as_ticks(), ticks);
+        }
+    }
+
+    #[test]
+    fn degrees_round_to_the_nearest_tick() {
+        assert_eq!(degrees_to_ticks(0.11), 0);
+        assert_eq!(degrees_to_ticks(0.13), 1);
+        assert_eq!(degrees_to_ticks(-0.13), -1);
+    }
+
+    #[test]
+    fn radians_and_degrees_agree_on_every_tick() {
+        for ticks in 0..=MAX_TICKS as i16 {
+            let degrees = ticks_to_degrees(ticks);
+            assert_eq!(radians_to_ticks(degrees.to_radians()), degrees_to_ticks(degrees), "{} ticks", ticks);
+            assert_eq!(radians_to_ticks(ticks_to_radians(ticks)), ticks);
+            assert_eq!(radians_to_position(ticks_to_radians(ticks)), degrees_to_position(degrees), "{} ticks", ticks);
+        }
+    }
+
+    #[test]
+    fn radian_edges_match_the_degree_edges() {
+        assert_eq!(radians_to_position(0.0), Some(0));
+        assert_eq!(radians_to_position(MAX_RADIANS), Some(1000));
+        assert_eq!(radians_to_position(-0.0002), None);
+        assert_eq!(radians_to_position(MAX_RADIANS + 0.0002), None);
+        assert_eq!(radians_to_position(f32::NAN), None);
+    }
+
+    #[test]
+    fn radian_constants_are_consistent() {
+        assert!((TICKS_PER_RADIAN * RADIANS_PER_TICK - 1.0).abs() < 1e-6);
+        assert!((MAX_RADIANS - MAX_TICKS as f32 * RADIANS_PER_TICK).abs() < 1e-5);
+        assert!((MAX_RADIANS - 4.0 * core::f32::consts::PI / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn only_0_to_1000_is_commandable() {
+        for ticks in i16::MIN..=i16::MAX {
+            let position = Position::from_ticks(ticks);
+            let commandable = (0..=1000).contains(&ticks);
+            assert_eq!(position.is_commandable(), commandable, "{}", ticks);
+            assert_eq!(position.to_wire(), commandable.then_some(ticks as u16), "{}", ticks);
+            assert_eq!(position.clamped().as_ticks(), ticks.clamp(0, 1000), "{}", ticks);
+        }
+    }
+
+    #[test]
+    fn wire_words_decode_as_twos_complement() {
+        for word in 0..=u16::MAX {
+            assert_eq!(Position::from_wire(word).as_ticks(), word as i16);
+        }
+        assert_eq!(Position::from_wire(0xFFFF), Position::from_ticks(-1));
+        assert_eq!(Position::from_wire(1000).to_wire(), Some(1000));
+    }
+
+    #[test]
+    fn raw_u16_ticks_saturate_rather_than_wrap() {
+        assert_eq!(Position::from(0u16), Position::MIN);
+        assert_eq!(Position::from(1000u16), Position::MAX);
+        assert_eq!(Position::from(40000u16).as_ticks(), i16::MAX);
+        assert_eq!(Position::from(40000u16).to_wire(), None);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn the_unsigned_units_api_still_works() {
+        assert_eq!(Position::from_units(500), Position::from_ticks(500));
+        assert_eq!(Position::from_units(40000), Position::from_ticks(i16::MAX));
+        assert_eq!(Position::from_ticks(1000).as_units(), 1000);
+        assert_eq!(Position::from_ticks(-3).as_units(), 0);
+        for units in [0, 1, 500, 999, 1000] {
+            assert_eq!(Position::from_units(units).as_units(), units);
+        }
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        assert_eq!(Position::CENTER + 100, Position::from_ticks(600));
+        assert_eq!(Position::MIN - 1, Position::from_ticks(-1));
+        assert_eq!(Position::from_ticks(i16::MAX) + 1, Position::from_ticks(i16::MAX));
+        assert_eq!(Position::from_ticks(i16::MIN) - 1, Position::from_ticks(i16::MIN));
+        assert_eq!(Position::MAX - Position::MIN, 1000);
+        assert_eq!(Position::from_ticks(i16::MIN) - Position::MAX, i16::MIN);
+        assert!(Position::MIN < Position::CENTER && Position::CENTER < Position::MAX);
+    }
+
+    #[test]
+    fn millivolts_convert_and_format_as_volts() {
+        assert_eq!(Millivolts(7420).as_volts(), 7.42);
+        assert_eq!(Millivolts(7420).to_string(), "7.42 V");
+        assert_eq!(Millivolts(12000).to_string(), "12.00 V");
+        assert_eq!(Millivolts(7416).to_string(), "7.42 V");
+        assert_eq!(u16::from(Millivolts::from(6500)), 6500);
+    }
+
+    #[test]
+    fn celsius_formats_with_the_unit() {
+        assert_eq!(Celsius(61).to_string(), "61 °C");
+        assert_eq!(Celsius(0).to_string(), "0 °C");
+        assert_eq!(u8::from(Celsius::from(85)), 85);
+    }
+
+    #[test]
+    fn readings_compare_against_raw_thresholds() {
+        assert!(Millivolts(6400) < 6500);
+        assert!(Millivolts(6500) == 6500 && Millivolts(6500) >= 6500);
+        assert!(Celsius(71) > 70);
+        assert!(Celsius(70) <= 70);
+        assert!(Celsius(40) < Celsius(41));
+    }
+}
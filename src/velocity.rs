@@ -0,0 +1,139 @@
+//! Angular velocity from successive position readings.
+
+use core::time::Duration;
+
+use crate::units::{self, Position};
+
+/// Finite-difference velocity estimate with an exponential filter.
+///
+/// The LX-16A doesn't report velocity, so feed it positions as they are read. The
+/// first reading only primes the estimator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelocityEstimator {
+    smoothing: f32,
+    last: Option<Position>,
+    velocity: Option<f32>,
+}
+
+impl VelocityEstimator {
+    /// Weight [`Default`] gives the newest difference.
+    pub const DEFAULT_SMOOTHING: f32 = 0.5;
+
+    /// `smoothing` is the weight of the newest difference against the running estimate;
+    /// 1.0 disables filtering. Values are clamped into (0, 1].
+    pub fn new(smoothing: f32) -> Self {
+        let smoothing = if smoothing.is_nan() { 1.0 } else { smoothing.clamp(f32::MIN_POSITIVE, 1.0) };
+        VelocityEstimator { smoothing, last: None, velocity: None }
+    }
+
+    /// Adds a reading taken `elapsed` after the previous one and returns the estimate in °/s.
+    ///
+    /// Readings with a zero `elapsed` replace the previous position without changing the estimate.
+    pub fn update(&mut self, position: Position, elapsed: Duration) -> Option<f32> {
+        let last = self.last.replace(position)?;
+        let secs = elapsed.as_secs_f32();
+        if secs == 0.0 {
+            return self.velocity;
+        }
+
+        let sample = units::ticks_to_degrees(position - last) / secs;
+        self.velocity = Some(match self.velocity {
+            Some(velocity) => velocity + self.smoothing * (sample - velocity),
+            None => sample,
+        });
+        self.velocity
+    }
+
+    /// Current estimate in °/s; `None` until two readings are in.
+    pub fn velocity(&self) -> Option<f32> {
+        self.velocity
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.velocity = None;
+    }
+}
+
+impl Default for VelocityEstimator {
+    fn default() -> Self {
+        VelocityEstimator::new(Self::DEFAULT_SMOOTHING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn the_first_reading_only_primes() {
+        let mut estimator = VelocityEstimator::default();
+        assert_eq!(estimator.update(Position::from_ticks(500), SECOND), None);
+        assert_eq!(estimator.velocity(), None);
+        assert_eq!(estimator.update(Position::from_ticks(600), SECOND), Some(24.0));
+    }
+
+    #[test]
+    fn a_constant_slope_gives_its_exact_rate() {
+        let mut estimator = VelocityEstimator::default();
+        estimator.update(Position::from_ticks(1000), Duration::ZERO);
+        // 50 ticks, 12°, every half second, downwards
+        for ticks in [950, 900, 850, 800] {
+            assert_eq!(estimator.update(Position::from_ticks(ticks), SECOND / 2), Some(-24.0));
+        }
+    }
+
+    #[test]
+    fn newer_differences_are_weighted_by_the_smoothing() {
+        let mut estimator = VelocityEstimator::new(0.25);
+        estimator.update(Position::from_ticks(0), Duration::ZERO);
+        assert_eq!(estimator.update(Position::from_ticks(100), SECOND), Some(24.0));
+        // 48°/s sample pulls the estimate a quarter of the way
+        assert_eq!(estimator.update(Position::from_ticks(300), SECOND), Some(30.0));
+
+        let mut unfiltered = VelocityEstimator::new(1.0);
+        unfiltered.update(Position::from_ticks(0), Duration::ZERO);
+        unfiltered.update(Position::from_ticks(100), SECOND);
+        assert_eq!(unfiltered.update(Position::from_ticks(300), SECOND), Some(48.0));
+    }
+
+    #[test]
+    fn a_zero_interval_keeps_the_estimate_but_moves_the_reference() {
+        let mut estimator = VelocityEstimator::new(1.0);
+        estimator.update(Position::from_ticks(0), Duration::ZERO);
+        estimator.update(Position::from_ticks(100), SECOND);
+        assert_eq!(estimator.update(Position::from_ticks(400), Duration::ZERO), Some(24.0));
+        // Measured from 400, not 100
+        assert_eq!(estimator.update(Position::from_ticks(500), SECOND), Some(24.0));
+    }
+
+    #[test]
+    fn smoothing_is_clamped_into_range() {
+        let fed = |smoothing: f32| {
+            let mut estimator = VelocityEstimator::new(smoothing);
+            estimator.update(Position::from_ticks(0), Duration::ZERO);
+            estimator.update(Position::from_ticks(100), SECOND);
+            estimator.update(Position::from_ticks(300), SECOND).unwrap()
+        };
+        // NaN and anything above 1 mean no filtering
+        assert_eq!(fed(f32::NAN), 48.0);
+        assert_eq!(fed(7.0), 48.0);
+        // Zero and below barely move off the first sample, but never freeze at NaN
+        for smoothing in [0.0, -1.0, f32::NEG_INFINITY] {
+            let velocity = fed(smoothing);
+            assert!((velocity - 24.0).abs() < 1e-3, "{} gave {}", smoothing, velocity);
+        }
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut estimator = VelocityEstimator::default();
+        estimator.update(Position::from_ticks(0), Duration::ZERO);
+        estimator.update(Position::from_ticks(100), SECOND);
+        estimator.reset();
+        assert_eq!(estimator.velocity(), None);
+        assert_eq!(estimator.update(Position::from_ticks(900), SECOND), None);
+    }
+}
@@ -0,0 +1,127 @@
+//! Recovering servos that reset and came back unloaded.
+
+use std::{io, sync::mpsc, thread, time::Duration};
+
+use log::{debug, warn};
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+
+// Move time used to bring a recovered servo back to its target, so it doesn't snap
+const RECOVERY_MOVE_MS: u16 = 500;
+
+/// Thread started by [`ServoController::start_watchdog`].
+///
+/// Every interval it reads the torque state of each watched servo. A servo that reads
+/// unloaded although this controller last left it loaded is taken to have reset (e.g.
+/// a brownout): it is sent back to its [`last_target`](ServoController::last_target)
+/// with torque re-enabled, moving there over 500 ms, or holds where it is when there is none.
+/// Servos deliberately unloaded through the controller are left alone.
+///
+/// Failed reads are logged and retried on the next round. Stops when [`stop`](Self::stop)
+/// is called or the watchdog is dropped.
+pub struct Watchdog {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub(crate) fn spawn(controller: ServoController, ids: Vec<u8>, interval: Duration) -> io::Result<Watchdog> {
+        let (stop, stopped) = mpsc::channel();
+        let stopper = stop.clone();
+        let running = controller.register_thread(move || { let _ = stopper.send(()); });
+        let thread = thread::Builder::new()
+            .name("lx16a-watchdog".to_string())
+            .spawn(move || {
+                let _running = running;
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    for &servo_id in &ids {
+                        if let Err(e) = check(&controller, servo_id.into()) {
+                            debug!("Watchdog check of servo {} failed: {}", servo_id, e);
+                        }
+                    }
+                }
+            })?;
+        Ok(Watchdog { stop: Some(stop), thread: Some(thread) })
+    }
+
+    /// Stops the thread, waiting for a round in progress to finish.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        // A clone of the sender is kept for ServoController::shutdown, so send rather than drop
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+fn check(controller: &ServoController, servo_id: ServoId) -> Result<(), ControllerError> {
+    let expected = controller.expected_torque(servo_id);
+    if expected != Some(true) || controller.is_torque_enabled(servo_id, None)? {
+        return Ok(());
+    }
+
+    warn!("Servo {} came back unloaded, restoring it", servo_id);
+    let restored = match controller.last_target(servo_id) {
+        // Loaded first, so it holds where it is and the move passes the torque check
+        Some(target) => controller.enable_torque(servo_id)
+            .and_then(|()| controller.move_servo(servo_id, target, RECOVERY_MOVE_MS)),
+        None => controller.hold_position(servo_id, None, None).map(|_| ()),
+    };
+    // The read above noted it unloaded; it is still meant to be loaded, so retry next round
+    if restored.is_err() {
+        controller.note_torque(servo_id, true);
+    }
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::*;
+
+    fn wait_for(mock: &MockTransport, what: &str, mut done: impl FnMut(&MockTransport) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while !done(mock) {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn a_failed_recovery_is_retried_on_the_next_round() {
+        let mock = MockTransport::new();
+        let controller = mock.controller(Duration::from_millis(5));
+        controller.enable_torque(1).unwrap();
+
+        // Reads unloaded; with no target to go back to the position read gets no answer
+        mock.reply_to(1, SERVO_LOAD_OR_UNLOAD_READ, MockReply::frame(1, SERVO_LOAD_OR_UNLOAD_READ, &[0]));
+        let _watchdog = controller.start_watchdog(&[1], Duration::from_millis(10)).unwrap();
+        wait_for(&mock, "the first recovery", |mock| mock.written().iter().any(|f| f.command == SERVO_POS_READ));
+        let failed_at = mock.written().len();
+
+        mock.reply_to(1, SERVO_LOAD_OR_UNLOAD_READ, MockReply::frame(1, SERVO_LOAD_OR_UNLOAD_READ, &[0]));
+        mock.reply_to(1, SERVO_POS_READ, MockReply::frame(1, SERVO_POS_READ, &[0xF4, 0x01]));
+        wait_for(&mock, "the retry to load the servo", |mock| {
+            mock.written()[failed_at..].iter()
+                .any(|f| f.command == SERVO_LOAD_OR_UNLOAD_WRITE && f.params() == [1])
+        });
+        let hold = mock.written().into_iter().rfind(|f| f.command == SERVO_MOVE_TIME_WRITE).unwrap();
+        assert_eq!(hold.params(), [0xF4, 0x01, 0, 0]);
+    }
+}
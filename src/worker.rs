@@ -0,0 +1,397 @@
+//! A thread that owns the bus and runs requests from other threads in order.
+
+use std::{collections::VecDeque, io, mem, sync::{mpsc, Arc, Condvar, Mutex}, thread, time::Duration};
+
+use crate::controller::ServoController;
+use crate::error::ControllerError;
+use crate::id::ServoId;
+use crate::types::ServoStatus;
+use crate::units::Position;
+
+type Job = Box<dyn FnOnce(&ServoController) + Send>;
+
+struct Queue {
+    jobs: VecDeque<Job>,
+    // Served before `jobs`
+    urgent: VecDeque<Job>,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+}
+
+impl Shared {
+    fn close(&self) {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.ready.notify_all();
+    }
+}
+
+// Closes the queue when the worker thread ends, panicking or not, dropping the jobs still
+// queued so their callers get WorkerStopped instead of waiting forever
+struct CloseOnExit<'a>(&'a Shared);
+
+impl Drop for CloseOnExit<'_> {
+    fn drop(&mut self) {
+        let dropped = {
+            let mut queue = self.0.queue.lock().unwrap_or_else(|e| e.into_inner());
+            queue.closed = true;
+            (mem::take(&mut queue.jobs), mem::take(&mut queue.urgent))
+        };
+        drop(dropped);
+    }
+}
+
+/// Runs every bus transaction on one worker thread, first come first served.
+///
+/// Callers submit through [`WorkerHandle`]s and block until their request has run, so
+/// a thread never holds the bus for someone else's round trip and requests from many
+/// threads are served strictly in submission order. Urgent requests (stops, unloads,
+/// [`submit_urgent`](WorkerHandle::submit_urgent)) go ahead of everything queued, but
+/// never interrupt the request already running.
+///
+/// [`shutdown`](Self::shutdown), or dropping the worker, runs what is already queued and
+/// then stops the thread; later submissions fail with `ControllerError::WorkerStopped`.
+/// [`ServoController::shutdown`] closes the queue the same way.
+pub struct BusWorker {
+    shared: Arc<Shared>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl BusWorker {
+    /// Starts the worker thread on `controller`. Other clones of the controller still
+    /// work but bypass the queue.
+    pub fn spawn(controller: ServoController) -> io::Result<BusWorker> {
+        let shared = Arc::new(Shared { queue: Mutex::new(Queue { jobs: VecDeque::new(), urgent: VecDeque::new(), closed: false }), ready: Condvar::new() });
+        let stopper = shared.clone();
+        let running = controller.register_thread(move || stopper.close());
+        let worker_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("lx16a-bus".to_string())
+            .spawn(move || {
+                let _running = running;
+                run(&worker_shared, &controller)
+            })?;
+        Ok(BusWorker { shared, thread: Some(thread) })
+    }
+
+    pub fn handle(&self) -> WorkerHandle {
+        WorkerHandle { shared: self.shared.clone() }
+    }
+
+    /// Stops taking requests, finishes those already queued and waits for the thread.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.shared.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for BusWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(shared: &Shared, controller: &ServoController) {
+    let _close = CloseOnExit(shared);
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if let Some(job) = queue.urgent.pop_front().or_else(|| queue.jobs.pop_front()) {
+                    break job;
+                }
+                if queue.closed {
+                    return;
+                }
+                queue = shared.ready.wait(queue).unwrap();
+            }
+        };
+        job(controller);
+    }
+}
+
+/// Submits requests to a [`BusWorker`]; clone one per thread.
+#[derive(Clone)]
+pub struct WorkerHandle {
+    shared: Arc<Shared>,
+}
+
+impl WorkerHandle {
+    /// Queues `f` to run on the worker thread and blocks until it has, returning its result.
+    pub fn submit<R: Send + 'static>(&self, f: impl FnOnce(&ServoController) -> Result<R, ControllerError> + Send + 'static) -> Result<R, ControllerError> {
+        self.enqueue(false, f)
+    }
+
+    /// [`submit`](Self::submit) ahead of every ordinary request still waiting; urgent
+    /// requests run in the order they were submitted.
+    pub fn submit_urgent<R: Send + 'static>(&self, f: impl FnOnce(&ServoController) -> Result<R, ControllerError> + Send + 'static) -> Result<R, ControllerError> {
+        self.enqueue(true, f)
+    }
+
+    fn enqueue<R: Send + 'static>(&self, urgent: bool, f: impl FnOnce(&ServoController) -> Result<R, ControllerError> + Send + 'static) -> Result<R, ControllerError> {
+        let (reply, result) = mpsc::sync_channel(1);
+        let job: Job = Box::new(move |controller| {
+            let _ = reply.send(f(controller));
+        });
+        {
+            let mut queue = self.shared.queue.lock().unwrap();
+            if queue.closed {
+                return Err(ControllerError::WorkerStopped);
+            }
+            if urgent { queue.urgent.push_back(job) } else { queue.jobs.push_back(job) }
+        }
+        self.shared.ready.notify_one();
+
+        // A closed channel means the worker died running a job
+        result.recv().unwrap_or(Err(ControllerError::WorkerStopped))
+    }
+
+    /// Requests waiting to run, not counting the one in progress.
+    pub fn queued(&self) -> usize {
+        let queue = self.shared.queue.lock().unwrap();
+        queue.jobs.len() + queue.urgent.len()
+    }
+
+    pub fn move_servo(&self, servo_id: impl Into<ServoId>, position: impl Into<Position>, time: u16) -> Result<(), ControllerError> {
+        let (servo_id, position) = (servo_id.into(), position.into());
+        self.submit(move |controller| controller.move_servo(servo_id, position, time))
+    }
+
+    /// Urgent, like [`stop_now`](Self::stop_now).
+    pub fn move_stop(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError> {
+        self.stop_now(servo_id)
+    }
+
+    /// Stops `servo_id` (or every servo, with the broadcast id) as the next request sent.
+    pub fn stop_now(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError> {
+        let servo_id = servo_id.into();
+        self.submit_urgent(move |controller| controller.move_stop(servo_id))
+    }
+
+    /// Unloads `servo_id` (or every servo) as the next request sent.
+    pub fn unload_now(&self, servo_id: impl Into<ServoId>) -> Result<(), ControllerError> {
+        let servo_id = servo_id.into();
+        self.submit_urgent(move |controller| controller.disable_torque(servo_id))
+    }
+
+    /// Unloading is urgent, like [`unload_now`](Self::unload_now); loading is not.
+    pub fn set_torque(&self, servo_id: impl Into<ServoId>, enabled: bool) -> Result<(), ControllerError> {
+        let servo_id = servo_id.into();
+        self.enqueue(!enabled, move |controller| controller.set_torque(servo_id, enabled))
+    }
+
+    pub fn get_position(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<Position, ControllerError> {
+        let servo_id = servo_id.into();
+        self.submit(move |controller| controller.get_position(servo_id, timeout))
+    }
+
+    pub fn get_status(&self, servo_id: impl Into<ServoId>, timeout: Option<Duration>) -> Result<ServoStatus, ControllerError> {
+        let servo_id = servo_id.into();
+        self.submit(move |controller| controller.get_status(servo_id, timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use super::*;
+    use crate::mock::{MockReply, MockTransport};
+    use crate::protocol::{Frame, MAX_FRAME_LEN, SERVO_LOAD_OR_UNLOAD_WRITE, SERVO_MOVE_STOP, SERVO_MOVE_TIME_WRITE, SERVO_POS_READ};
+
+    fn worker() -> BusWorker {
+        BusWorker::spawn(MockTransport::new().controller(Duration::from_millis(20))).unwrap()
+    }
+
+    fn wait_for_queued(handle: &WorkerHandle, count: usize) {
+        while handle.queued() < count {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn a_panicking_job_stops_the_worker_instead_of_hanging_callers() {
+        let worker = worker();
+        let handle = worker.handle();
+
+        let (release, wait) = mpsc::channel::<()>();
+        let (started, running) = mpsc::channel::<()>();
+        let panicking = {
+            let handle = handle.clone();
+            thread::spawn(move || handle.submit(move |_| -> Result<(), ControllerError> {
+                started.send(()).unwrap();
+                let _ = wait.recv();
+                panic!("job failed");
+            }))
+        };
+        running.recv().unwrap();
+        let queued = {
+            let handle = handle.clone();
+            thread::spawn(move || handle.submit(|_| Ok(())))
+        };
+        wait_for_queued(&handle, 1);
+        release.send(()).unwrap();
+
+        assert!(matches!(panicking.join().unwrap(), Err(ControllerError::WorkerStopped)));
+        assert!(matches!(queued.join().unwrap(), Err(ControllerError::WorkerStopped)));
+        assert!(matches!(handle.submit(|_| Ok(())), Err(ControllerError::WorkerStopped)));
+    }
+
+    // Holds the worker in a job until the returned sender is dropped
+    fn block(handle: &WorkerHandle) -> (mpsc::Sender<()>, thread::JoinHandle<Result<(), ControllerError>>) {
+        let (release, wait) = mpsc::channel::<()>();
+        let (started, running) = mpsc::channel::<()>();
+        let blocker = {
+            let handle = handle.clone();
+            thread::spawn(move || handle.submit(move |_| {
+                started.send(()).unwrap();
+                let _ = wait.recv();
+                Ok(())
+            }))
+        };
+        running.recv().unwrap();
+        (release, blocker)
+    }
+
+    #[test]
+    fn requests_from_many_threads_run_in_submission_order() {
+        let worker = worker();
+        let handle = worker.handle();
+        let (release, blocker) = block(&handle);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let submitters: Vec<_> = (0..16).map(|index| {
+            let (submitting, order) = (handle.clone(), order.clone());
+            let submitter = thread::spawn(move || submitting.submit(move |_| {
+                order.lock().unwrap().push(index);
+                Ok(())
+            }));
+            // One at a time, so the submission order is known
+            wait_for_queued(&handle, index + 1);
+            submitter
+        }).collect();
+
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        for submitter in submitters {
+            submitter.join().unwrap().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn threads_mixing_moves_and_reads_through_the_worker_never_mix_frames() {
+        const THREADS: u8 = 8;
+        const ROUNDS: i16 = 50;
+        let mock = MockTransport::new();
+        for servo_id in 1..=THREADS {
+            for round in 0..ROUNDS {
+                let ticks = servo_id as i16 * 100 + round;
+                mock.reply_to(servo_id, SERVO_POS_READ, MockReply::frame(servo_id, SERVO_POS_READ, &ticks.to_le_bytes()));
+            }
+        }
+        let worker = BusWorker::spawn(mock.controller(Duration::from_millis(20))).unwrap();
+
+        let threads: Vec<_> = (1..=THREADS).map(|servo_id| {
+            let handle = worker.handle();
+            thread::spawn(move || for round in 0..ROUNDS {
+                handle.move_servo(servo_id, 500u16, 0).unwrap();
+                let position = handle.get_position(servo_id, None).unwrap();
+                assert_eq!(position.as_ticks(), servo_id as i16 * 100 + round);
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(mock.unused_replies(), 0);
+        let written = mock.written();
+        assert_eq!(written.len(), THREADS as usize * 2 * ROUNDS as usize);
+        // Every byte on the wire belongs to a whole frame, in order
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        let frames: Vec<u8> = written.iter().flat_map(|frame: &Frame| frame.encode(&mut buf).to_vec()).collect();
+        assert_eq!(mock.written_bytes(), frames);
+    }
+
+    #[test]
+    fn a_stop_behind_50_queued_queries_is_the_next_packet_written() {
+        let mock = MockTransport::new();
+        // The query in flight answers late, so the others queue up behind it
+        mock.reply_to(1, SERVO_POS_READ, MockReply::frame(1, SERVO_POS_READ, &[0xF4, 0x01]).after(Duration::from_millis(200)));
+        for _ in 0..50 {
+            mock.reply_to(2, SERVO_POS_READ, MockReply::frame(2, SERVO_POS_READ, &[0xF4, 0x01]).after(Duration::from_millis(2)));
+        }
+        let worker = BusWorker::spawn(mock.controller(Duration::from_millis(500))).unwrap();
+        let handle = worker.handle();
+
+        let in_flight = {
+            let handle = handle.clone();
+            thread::spawn(move || handle.get_position(1, None))
+        };
+        while mock.written().is_empty() {
+            thread::yield_now();
+        }
+        let queries: Vec<_> = (0..50).map(|_| {
+            let handle = handle.clone();
+            thread::spawn(move || handle.get_position(2, None))
+        }).collect();
+        wait_for_queued(&handle, 50);
+        handle.stop_now(3).unwrap();
+
+        in_flight.join().unwrap().unwrap();
+        for query in queries {
+            query.join().unwrap().unwrap();
+        }
+        let written: Vec<_> = mock.written().iter().map(|frame| (frame.servo_id, frame.command)).collect();
+        assert_eq!(written[..2], [(1, SERVO_POS_READ), (3, SERVO_MOVE_STOP)]);
+        assert!(written[2..].iter().all(|&frame| frame == (2, SERVO_POS_READ)));
+        assert_eq!(written.len(), 52);
+    }
+
+    // Submits `f` from a new thread and waits until `queued` requests are waiting
+    fn submit_queued(handle: &WorkerHandle, queued: usize, f: impl FnOnce(&WorkerHandle) -> Result<(), ControllerError> + Send + 'static) -> thread::JoinHandle<Result<(), ControllerError>> {
+        let submitting = handle.clone();
+        let submitter = thread::spawn(move || f(&submitting));
+        wait_for_queued(handle, queued);
+        submitter
+    }
+
+    #[test]
+    fn unloading_jumps_the_queue_but_loading_does_not() {
+        let mock = MockTransport::new();
+        let worker = BusWorker::spawn(mock.controller(Duration::from_millis(20))).unwrap();
+        let handle = worker.handle();
+        let (release, blocker) = block(&handle);
+
+        let submitters = [
+            submit_queued(&handle, 1, |handle| handle.move_servo(1, 500u16, 0)),
+            submit_queued(&handle, 2, |handle| handle.set_torque(2, true)),
+            submit_queued(&handle, 3, |handle| handle.unload_now(3)),
+            submit_queued(&handle, 4, |handle| handle.set_torque(4, false)),
+            submit_queued(&handle, 5, |handle| handle.move_stop(5)),
+        ];
+        drop(release);
+        blocker.join().unwrap().unwrap();
+        for submitter in submitters {
+            submitter.join().unwrap().unwrap();
+        }
+
+        let written: Vec<_> = mock.written().iter().map(|frame| (frame.servo_id, frame.command)).collect();
+        assert_eq!(written, [
+            (3, SERVO_LOAD_OR_UNLOAD_WRITE),
+            (4, SERVO_LOAD_OR_UNLOAD_WRITE),
+            (5, SERVO_MOVE_STOP),
+            (1, SERVO_MOVE_TIME_WRITE),
+            (2, SERVO_LOAD_OR_UNLOAD_WRITE),
+        ]);
+    }
+}